@@ -1,32 +1,12 @@
 local utils = require("utils")
 
-local start = {-1, 56, 0}
-local direction = "north"
-local radius = 3
-local currentFold = 1
-local current = 2
-local p = start
-
-function proceed()
-    p = utils.forward(p, direction)
-    current = current + 1
-    if current >= radius then
-        if currentFold == 3 then
-            radius = radius + 1
-            currentFold = 1
-        end
-        current = 1
-        direction = utils.NEXT_DIRECTION[direction]
-        currentFold = currentFold + 1
-    end
-end
+-- What earlier requests of this connection set up, such as the dimension.
+local session = {}
 
 local address = "ws://localhost:8765"
-local ws, connected = http.websocket(address)
-if ws then
-    ws.send("ser")
-else
-    print("Failed to connect to websocket")
+local ws, err = utils.connect(address, "ser")
+if not ws then
+    printError("Failed to connect to websocket: " .. tostring(err))
     return
 end
 
@@ -39,11 +19,7 @@ while true do
 
     elseif event == "websocket_message" then
         print(cmd)
-        utils.place_block(p, cmd)
-        if ws then
-            ws.send("1")
-        end
-        proceed()
+        ws.send(utils.respond(cmd, session))
 
     elseif event == "websocket_closed" then
         print("Websocket closed.")
@@ -1,45 +1,189 @@
 local lib = {}
 
-lib.NEXT_DIRECTION = {
-    north = "east",
-    east = "south",
-    south = "west",
-    west = "north"
-}
-
-lib.PREV_DIRECTION = {
-    north = "west",
-    east = "north",
-    south = "east",
-    west = "south"
-}
-
-function lib.forward(pos, direction)
-    if direction == "north" then
-        return {pos[1], pos[2], pos[3] - 1}
-    elseif direction == "east" then
-        return {pos[1] + 1, pos[2], pos[3]}
-    elseif direction == "south" then
-        return {pos[1], pos[2], pos[3] + 1}
-    elseif direction == "west" then
-        return {pos[1] - 1, pos[2], pos[3]}
-    end
-end
-
-function lib.backward(pos, direction)
-    if direction == "north" then
-        return {pos[1], pos[2], pos[3] + 1}
-    elseif direction == "east" then
-        return {pos[1] - 1, pos[2], pos[3]}
-    elseif direction == "south" then
-        return {pos[1], pos[2], pos[3] - 1}
-    elseif direction == "west" then
-        return {pos[1] + 1, pos[2], pos[3]}
-    end
-end
-
-function lib.place_block(pos, block)
-    commands.execAsync("setblock " .. pos[1] .. " " .. pos[2] .. " " .. pos[3] .. " " .. block)
+-- The version of the protocol spoken with the Rust application.
+lib.PROTOCOL_VERSION = 2
+
+-- The optional commands these scripts understand.
+lib.CAPABILITIES = {"read", "fill", "forceload", "dimension", "resume"}
+
+-- Where the number of blocks placed so far is kept for each unfinished build, so that it
+-- survives a reboot.
+local CURSORS_FILE = "serdecraft.cursors"
+
+-- The blocks placed so far of every unfinished build, by the key the Rust application gave it.
+local function load_cursors()
+    if not fs.exists(CURSORS_FILE) then
+        return {}
+    end
+
+    local file = fs.open(CURSORS_FILE, "r")
+    local cursors = textutils.unserialise(file.readAll())
+    file.close()
+    return type(cursors) == "table" and cursors or {}
+end
+
+local function save_cursor(build, cursor)
+    local cursors = load_cursors()
+    cursors[build] = cursor
+
+    local file = fs.open(CURSORS_FILE, "w")
+    file.write(textutils.serialise(cursors))
+    file.close()
+end
+
+-- Run a command in another dimension than the computer's when one is given.
+local function in_dimension(command, dimension)
+    if dimension then
+        return "execute in " .. dimension .. " run " .. command
+    end
+    return command
+end
+
+local function coordinates(pos)
+    return pos.x .. " " .. pos.y .. " " .. pos.z
+end
+
+-- Run a command and wait for the game to finish it.
+-- Returns whether it worked and, if not, the error text the game reported.
+local function run(command, dimension)
+    local task = commands.execAsync(in_dimension(command, dimension))
+
+    while true do
+        local _, id, ran, success, output = os.pullEvent("task_complete")
+        if id == task then
+            if not ran then
+                return false, tostring(success)
+            end
+            return success, table.concat(output or {}, " ")
+        end
+    end
+end
+
+function lib.place_block(pos, block, dimension)
+    return run("setblock " .. coordinates(pos) .. " " .. block, dimension)
+end
+
+function lib.fill(from, to, block, dimension)
+    return run("fill " .. coordinates(from) .. " " .. coordinates(to) .. " " .. block, dimension)
+end
+
+-- The error `commands.getBlockInfo` raises for a block in a chunk that is not loaded.
+local UNLOADED = "Block is not loaded"
+
+-- The name of the block at a position, or nil and whether its chunk is not loaded along with
+-- the error otherwise.
+function lib.read_block(pos, dimension)
+    local ok, block = pcall(commands.getBlockInfo, pos.x, pos.y, pos.z, dimension)
+    if ok then
+        return block.name
+    end
+    return nil, string.find(tostring(block), UNLOADED, 1, true) ~= nil, tostring(block)
+end
+
+-- Add or remove the forceload of the chunk holding a block column.
+function lib.forceload(action, x, z, dimension)
+    commands.exec(in_dimension("forceload " .. action .. " " .. x .. " " .. z, dimension))
+end
+
+-- Carry out a request from the Rust application, returning the reply to it.
+-- `session` keeps what earlier requests of the connection set up.
+local function handle(request, session)
+    if request.version ~= lib.PROTOCOL_VERSION then
+        return {type = "error", message = "unsupported protocol version " .. tostring(request.version)}
+    end
+
+    if request.type == "dimension" then
+        session.dimension = request.id
+        return {type = "done"}
+
+    elseif request.type == "begin" then
+        session.build = request.build
+        return {type = "done"}
+
+    elseif request.type == "complete" then
+        save_cursor(request.build, nil)
+        session.build = nil
+        return {type = "done"}
+
+    elseif request.type == "forceload" then
+        lib.forceload(request.action, request.x, request.z, session.dimension)
+        return {type = "done"}
+
+    elseif request.type == "read" then
+        local name, unloaded, message = lib.read_block(request.position, session.dimension)
+        if name then
+            return {type = "block", name = name}
+        elseif unloaded then
+            return {type = "unloaded"}
+        end
+        return {type = "error", message = message}
+
+    elseif request.type == "place" then
+        local ok, message = lib.place_block(request.position, request.block, session.dimension)
+        if ok and session.build then
+            save_cursor(session.build, request.cursor + 1)
+        end
+        if ok then
+            return {type = "placed", position = request.position}
+        end
+        return {type = "failed", message = message}
+
+    elseif request.type == "fill" then
+        local ok, message = lib.fill(request.from, request.to, request.block, session.dimension)
+        if ok then
+            return {type = "done"}
+        end
+        return {type = "error", message = message}
+    end
+
+    return {type = "error", message = "unknown request " .. tostring(request.type)}
+end
+
+-- Connect to the Rust application as `role` ("ser" or "de") and introduce ourselves.
+-- Returns the websocket, or nil and the reason the connection failed or was refused.
+function lib.connect(address, role)
+    local ws, err = http.websocket(address)
+    if not ws then
+        return nil, err
+    end
+
+    -- Only a serializing computer resumes builds, and an empty table would be sent as a list.
+    local cursors = nil
+    if role == "ser" and next(load_cursors()) then
+        cursors = load_cursors()
+    end
+
+    ws.send(textutils.serialiseJSON({
+        type = "hello",
+        version = lib.PROTOCOL_VERSION,
+        role = role,
+        capabilities = lib.CAPABILITIES,
+        cursors = cursors,
+        id = os.getComputerID(),
+        label = os.getComputerLabel(),
+        -- Set with `set serdecraft.token <token>` when the Rust application asks for one.
+        token = settings.get("serdecraft.token"),
+    }))
+
+    local answer = textutils.unserialiseJSON(ws.receive() or "null")
+    if type(answer) ~= "table" or answer.type ~= "welcome" then
+        ws.close()
+        return nil, type(answer) == "table" and answer.message or "no answer to hello"
+    end
+    return ws
+end
+
+-- Answer a JSON request frame with a JSON reply frame, echoing its sequence number and cursor.
+function lib.respond(frame, session)
+    local request = textutils.unserialiseJSON(frame)
+    if type(request) ~= "table" then
+        return textutils.serialiseJSON({type = "error", message = "malformed request"})
+    end
+
+    local reply = handle(request, session)
+    reply.seq = request.seq
+    reply.cursor = request.cursor
+    return textutils.serialiseJSON(reply)
 end
 
 return lib
@@ -1,7 +1,5 @@
 use serde::{Deserialize, Serialize};
-use serdecraft::MinecraftDeserializer;
-use std::net::TcpListener;
-use tungstenite::accept;
+use serdecraft::MinecraftServer;
 
 #[derive(Serialize, Deserialize, Debug)]
 enum Direction {
@@ -27,15 +25,10 @@ struct UserWithProfile {
 }
 
 fn main() {
-    let server = TcpListener::bind("127.0.0.1:8765").unwrap();
-    for stream in server.incoming() {
-        let websocket = accept(stream.unwrap()).unwrap();
-        println!("WebSocket connection established!");
-
-        let mut deserializer = MinecraftDeserializer::new(websocket);
-        match <UserWithProfile>::deserialize(&mut deserializer) {
-            Ok(x) => println!("Deserialized: {:#?}", x),
-            Err(e) => println!("Failed to deserialize: {:#?}", e),
-        }
-    }
+    env_logger::init();
+    MinecraftServer::bind("127.0.0.1:8765")
+        .unwrap()
+        .deserialize(|x: UserWithProfile| println!("Deserialized: {:#?}", x))
+        .run()
+        .unwrap();
 }
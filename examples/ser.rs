@@ -1,6 +1,4 @@
-use serdecraft::MinecraftSerializer;
-use std::net::TcpListener;
-use tungstenite::accept;
+use serdecraft::MinecraftServer;
 
 use serde::{Deserialize, Serialize};
 
@@ -39,12 +37,10 @@ fn main() {
         },
     };
 
-    let server = TcpListener::bind("127.0.0.1:8765").unwrap();
-    for stream in server.incoming() {
-        let websocket = accept(stream.unwrap()).unwrap();
-        println!("WebSocket connection established!");
-
-        let mut serializer = MinecraftSerializer::new(websocket);
-        v.serialize(&mut serializer).unwrap();
-    }
+    env_logger::init();
+    MinecraftServer::bind("127.0.0.1:8765")
+        .unwrap()
+        .serialize(v)
+        .run()
+        .unwrap();
 }
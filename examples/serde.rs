@@ -1,6 +1,4 @@
-use serdecraft::{MinecraftDeserializer, MinecraftResult, MinecraftSerializer};
-use std::net::TcpListener;
-use tungstenite::accept;
+use serdecraft::{MinecraftResult, MinecraftServer};
 
 use serde::{Deserialize, Serialize};
 
@@ -39,45 +37,9 @@ fn main() -> MinecraftResult<()> {
         },
     };
 
-    let server = TcpListener::bind("127.0.0.1:8765")?;
-    for stream in server.incoming() {
-        let mut websocket = accept(stream?).unwrap();
-        println!("WebSocket connection established!");
-
-        let message = match websocket.read() {
-            Ok(msg) => msg,
-            Err(err) => {
-                eprintln!("Failed to read message: {:?}", err);
-                continue;
-            }
-        };
-
-        let text = match message.to_text() {
-            Ok(text) => text,
-            Err(_) => {
-                eprintln!("Failed to convert message to text");
-                continue;
-            }
-        };
-
-        match text {
-            "ser" => {
-                let mut serializer = MinecraftSerializer::new(websocket);
-                match v.serialize(&mut serializer) {
-                    Ok(_) => println!("Serialized successfully"),
-                    Err(e) => eprintln!("Failed to serialize: {:?}", e),
-                };
-            }
-            "de" => {
-                let mut deserializer = MinecraftDeserializer::new(websocket);
-                match <UserWithProfile>::deserialize(&mut deserializer) {
-                    Ok(x) => println!("Deserialized: {:#?}", x),
-                    Err(e) => eprintln!("Failed to deserialize: {:?}", e),
-                }
-            }
-            _ => eprintln!("Unknown command"),
-        }
-    }
-
-    Ok(())
+    env_logger::init();
+    MinecraftServer::bind("127.0.0.1:8765")?
+        .serialize(v)
+        .deserialize(|x: UserWithProfile| println!("Deserialized: {:#?}", x))
+        .run()
 }
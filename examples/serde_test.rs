@@ -1,9 +1,8 @@
 #![feature(more_float_constants)]
 #![feature(hash_map_macro)]
 
-use serdecraft::{MinecraftDeserializer, MinecraftResult, MinecraftSerializer};
-use std::{collections::HashMap, f32::consts::PI, f64::consts::PHI, net::TcpListener};
-use tungstenite::accept;
+use serdecraft::{MinecraftResult, MinecraftServer};
+use std::{collections::HashMap, f32::consts::PI, f64::consts::PHI, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
@@ -131,46 +130,15 @@ fn main() -> MinecraftResult<()> {
         tuple_variant: TupleVariant::Rgb(0, 1, 2),
     };
 
-    let server = TcpListener::bind("127.0.0.1:8765")?;
+    let v = Arc::new(v);
+    let expected = Arc::clone(&v);
+
     println!("Started");
-    for stream in server.incoming() {
-        let mut websocket = accept(stream?).unwrap();
-        println!("WebSocket connection established!");
-
-        let message = match websocket.read() {
-            Ok(msg) => msg,
-            Err(err) => {
-                eprintln!("Failed to read message: {:?}", err);
-                continue;
-            }
-        };
-
-        let text = match message.to_text() {
-            Ok(text) => text,
-            Err(_) => {
-                eprintln!("Failed to convert message to text");
-                continue;
-            }
-        };
-
-        match text {
-            "ser" => {
-                let mut serializer = MinecraftSerializer::new(websocket);
-                match v.serialize(&mut serializer) {
-                    Ok(_) => println!("Serialized successfully"),
-                    Err(e) => eprintln!("Failed to serialize: {:?}", e),
-                };
-            }
-            "de" => {
-                let mut deserializer = MinecraftDeserializer::new(websocket);
-                match <TestStructure>::deserialize(&mut deserializer) {
-                    Ok(x) => assert_eq!(v, x),
-                    Err(e) => eprintln!("Failed to deserialize: {:?}", e),
-                }
-            }
-            _ => eprintln!("Unknown command"),
-        }
-    }
-
-    Ok(())
+    MinecraftServer::bind("127.0.0.1:8765")?
+        .on_ser(move |mut serializer| {
+            v.serialize(&mut serializer)?;
+            serializer.finish()
+        })
+        .deserialize(move |x: TestStructure| assert_eq!(*expected, x))
+        .run()
 }
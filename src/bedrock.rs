@@ -0,0 +1,342 @@
+//! A [`Transport`] for Bedrock and Education Edition clients, which connect to a websocket server
+//! with `/connect` and run the commands they are sent.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    net::{TcpListener, TcpStream},
+};
+
+use serde_json::{Value, json};
+use tungstenite::WebSocket;
+
+use crate::{
+    Command, Dimension, ForceloadAction, MinecraftBlock, MinecraftError, MinecraftResult, Position,
+    Reply, Request, Response, Transport, UNKNOWN_BLOCK,
+};
+
+/// The message `testforblock` answers with when the position is in a chunk that is not loaded.
+const UNLOADED: &str = "Cannot access blocks in an unloaded chunk";
+
+/// A Bedrock block id, written the way `setblock`, `fill` and `testforblock` take it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BedrockBlock {
+    pub name: String,
+}
+
+impl BedrockBlock {
+    /// The Bedrock block standing in for a Java block id.
+    ///
+    /// Since the flattening of Bedrock ids, most blocks have the same id in both editions, such
+    /// as `minecraft:red_wool` or `minecraft:acacia_log`.
+    pub fn from_java(id: &str) -> Self {
+        let name = id.strip_prefix("minecraft:").unwrap_or(id);
+        let name = match name {
+            "light_gray_glazed_terracotta" => "silver_glazed_terracotta",
+            name => name,
+        };
+        Self {
+            name: format!("minecraft:{name}"),
+        }
+    }
+}
+
+impl Display for BedrockBlock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+impl MinecraftBlock {
+    /// The Bedrock Edition block for this block.
+    pub fn bedrock(&self) -> BedrockBlock {
+        BedrockBlock::from_java(&self.to_string())
+    }
+}
+
+/// A websocket opened by a Bedrock client with `/connect`, carrying out requests with
+/// `setblock`, `fill`, `tickingarea` and `testforblock` command requests.
+///
+/// Block ids are translated with [`BedrockBlock::from_java`]. There is no handshake; pass it
+/// straight to [`crate::MinecraftSerializer::new`] or [`crate::MinecraftDeserializer::new`].
+/// Reading a block tests it against every block this crate places, so reads take many commands
+/// each.
+pub struct Bedrock {
+    transport: Box<dyn Transport>,
+    id: u64,
+
+    /// The dimension later commands run in, unless it is the overworld.
+    dimension: Option<&'static str>,
+    responses: VecDeque<Response>,
+}
+
+impl Bedrock {
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Box::new(transport),
+            id: 0,
+            dimension: None,
+            responses: VecDeque::new(),
+        }
+    }
+
+    /// Wait for a client to run `/connect` with the address of `listener`.
+    pub fn accept(listener: &TcpListener) -> MinecraftResult<Self> {
+        let (stream, _) = listener.accept()?;
+        let socket: WebSocket<TcpStream> =
+            tungstenite::accept(stream).map_err(|e| MinecraftError::Handshake(e.to_string()))?;
+        Ok(Self::new(socket))
+    }
+
+    /// Run `command` on the client and return the status code and message it answered with.
+    pub fn command(&mut self, command: &str) -> MinecraftResult<(i64, String)> {
+        self.id += 1;
+        let id = format!("00000000-0000-4000-8000-{:012x}", self.id);
+        let request = json!({
+            "header": {
+                "version": 1,
+                "requestId": id,
+                "messagePurpose": "commandRequest",
+                "messageType": "commandRequest",
+            },
+            "body": {
+                "version": 1,
+                "commandLine": command,
+                "origin": { "type": "player" },
+            },
+        });
+        self.transport.send_frame(request.to_string())?;
+
+        // The client also sends the events it was subscribed to; skip anything else.
+        loop {
+            let frame: Value = serde_json::from_str(&self.transport.receive_frame()?)?;
+            if frame["header"]["requestId"] != id.as_str() {
+                continue;
+            }
+
+            let body = &frame["body"];
+            let status = body["statusCode"].as_i64().unwrap_or(-1);
+            let message = body["statusMessage"].as_str().unwrap_or_default();
+            return Ok((status, message.to_string()));
+        }
+    }
+
+    /// Run `command` in the chosen dimension.
+    fn run(&mut self, command: &str) -> MinecraftResult<(i64, String)> {
+        match self.dimension {
+            Some(dimension) => self.command(&format!("execute in {dimension} run {command}")),
+            None => self.command(command),
+        }
+    }
+
+    /// Whether the block at `position` is `block`, or `None` if its chunk is not loaded.
+    fn test(&mut self, position: Position, block: &BedrockBlock) -> MinecraftResult<Option<bool>> {
+        let Position { x, y, z } = position;
+        let (status, message) = self.run(&format!("testforblock {x} {y} {z} {block}"))?;
+        match message.as_str() {
+            UNLOADED => Ok(None),
+            _ => Ok(Some(status == 0)),
+        }
+    }
+
+    fn reply(&mut self, command: Command) -> MinecraftResult<Reply> {
+        Ok(match command {
+            Command::Dimension { id } => {
+                self.dimension = match id.as_str() {
+                    id if id == Dimension::Nether.id() => Some("nether"),
+                    id if id == Dimension::End.id() => Some("the_end"),
+                    _ => None,
+                };
+                Reply::Done
+            }
+            // Nothing is kept between sessions to resume from.
+            Command::Begin { .. } | Command::Complete { .. } => Reply::Done,
+            Command::Forceload { action, x, z } => {
+                let command = match action {
+                    ForceloadAction::Add => format!("tickingarea add {x} 0 {z} {x} 0 {z}"),
+                    ForceloadAction::Remove => format!("tickingarea remove {x} 0 {z}"),
+                };
+                self.run(&command)?;
+                Reply::Done
+            }
+            Command::Read { position } => {
+                let candidates = MinecraftBlock::ALL.iter().map(ToString::to_string);
+                let mut name = UNKNOWN_BLOCK.to_string();
+                for candidate in std::iter::once("minecraft:air".to_string()).chain(candidates) {
+                    match self.test(position, &BedrockBlock::from_java(&candidate))? {
+                        None => return Ok(Reply::Unloaded),
+                        Some(true) => {
+                            name = candidate;
+                            break;
+                        }
+                        Some(false) => {}
+                    }
+                }
+                Reply::Block { name }
+            }
+            Command::Place { position, block } => {
+                let Position { x, y, z } = position;
+                let block = BedrockBlock::from_java(&block);
+                let (status, message) = self.run(&format!("setblock {x} {y} {z} {block}"))?;
+
+                // `setblock` fails when the block is already there, which is as good as placed.
+                if status == 0 || self.test(position, &block)? == Some(true) {
+                    Reply::Placed { position }
+                } else {
+                    Reply::Failed { message }
+                }
+            }
+            Command::Fill { from, to, block } => {
+                let block = BedrockBlock::from_java(&block);
+                let (status, message) = self.run(&format!(
+                    "fill {} {} {} {} {} {} {block}",
+                    from.x, from.y, from.z, to.x, to.y, to.z
+                ))?;
+
+                match status {
+                    0 => Reply::Done,
+                    _ => Reply::Error { message },
+                }
+            }
+        })
+    }
+}
+
+impl Transport for Bedrock {
+    fn send_frame(&mut self, frame: String) -> MinecraftResult<()> {
+        let request: Request = serde_json::from_str(&frame)?;
+        let reply = self.reply(request.command)?;
+        self.responses.push_back(Response {
+            seq: request.seq,
+            cursor: request.cursor,
+            reply,
+        });
+        Ok(())
+    }
+
+    fn receive_frame(&mut self) -> MinecraftResult<String> {
+        let response = self.responses.pop_front().ok_or_else(|| {
+            MinecraftError::Bedrock("no request for the client to answer".to_string())
+        })?;
+        Ok(serde_json::to_string(&response)?)
+    }
+}
+
+#[test]
+fn bedrock_test() {
+    use crate::{MinecraftDeserializer, MinecraftSerializer};
+    use serde::{Deserialize, Serialize};
+    use std::{collections::HashMap, thread};
+    use tungstenite::Message;
+
+    assert_eq!(
+        MinecraftBlock::RedWool.bedrock().to_string(),
+        "minecraft:red_wool"
+    );
+    assert_eq!(
+        MinecraftBlock::LightGrayGlazedTerracotta
+            .bedrock()
+            .to_string(),
+        "minecraft:silver_glazed_terracotta"
+    );
+
+    // Ids from before the flattening take a data value, which no block may need.
+    let legacy = [
+        "wool",
+        "concrete",
+        "stained_glass",
+        "stained_hardened_clay",
+        "planks",
+        "log",
+        "log2",
+        "fence",
+        "seaLantern",
+        "brick_block",
+    ];
+    let mut names = std::collections::HashSet::new();
+    for block in MinecraftBlock::ALL {
+        let name = block.bedrock().name;
+        let id = name.strip_prefix("minecraft:").unwrap();
+        assert!(!legacy.contains(&id), "{block} maps to {name}");
+        assert!(names.insert(name), "{block} shares its Bedrock id");
+    }
+
+    // A client that has run `/connect` and keeps its world as the `setblock` arguments it got.
+    let client = |listener: &TcpListener, mut world: HashMap<Position, String>| {
+        let address = format!("ws://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut socket, _) = tungstenite::connect(address).unwrap();
+            let event = json!({ "header": { "messagePurpose": "event" }, "body": {} });
+            socket.send(Message::text(event.to_string())).unwrap();
+
+            while let Ok(Message::Text(text)) = socket.read() {
+                let request: Value = serde_json::from_str(&text).unwrap();
+                let line = request["body"]["commandLine"].as_str().unwrap();
+                let words: Vec<&str> = line.split(' ').collect();
+                let at = || {
+                    let n = |i: usize| words[i].parse().unwrap();
+                    Position::new(n(1), n(2), n(3))
+                };
+                let block = || words[4..].join(" ");
+                let found = |world: &HashMap<Position, String>| {
+                    world
+                        .get(&at())
+                        .cloned()
+                        .unwrap_or("minecraft:air".to_string())
+                };
+
+                let (status, message) = match words[0] {
+                    "testforblock" if found(&world) == "unloaded" => (-2147352576, UNLOADED),
+                    "setblock" if found(&world) == block() => (-2147352576, ""),
+                    "setblock" => {
+                        world.insert(at(), block());
+                        (0, "")
+                    }
+                    "testforblock" if found(&world) == block() => (0, ""),
+                    "testforblock" => (-2147352576, ""),
+                    _ => (0, ""),
+                };
+                let response = json!({
+                    "header": {
+                        "requestId": request["header"]["requestId"],
+                        "messagePurpose": "commandResponse",
+                    },
+                    "body": { "statusCode": status, "statusMessage": message },
+                });
+                socket.send(Message::text(response.to_string())).unwrap();
+            }
+            world
+        })
+    };
+
+    let value = vec![Some(3u8), None];
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+    let computer = client(&listener, HashMap::new());
+    let mut serializer =
+        MinecraftSerializer::new(Bedrock::accept(&listener).unwrap()).with_verify(true);
+    value.serialize(&mut serializer).unwrap();
+    serializer.finish().unwrap();
+    drop(serializer);
+    let world = computer.join().unwrap();
+    assert!(world.values().any(|block| block.ends_with("_wool")));
+
+    let unloaded = world.keys().map(|&p| (p, "unloaded".to_string())).collect();
+    let computer = client(&listener, world);
+    let mut deserializer = MinecraftDeserializer::new(Bedrock::accept(&listener).unwrap());
+    assert_eq!(
+        Vec::<Option<u8>>::deserialize(&mut deserializer).unwrap(),
+        value
+    );
+    drop(deserializer);
+    computer.join().unwrap();
+
+    let computer = client(&listener, unloaded);
+    let mut deserializer = MinecraftDeserializer::new(Bedrock::accept(&listener).unwrap());
+    assert!(matches!(
+        Vec::<Option<u8>>::deserialize(&mut deserializer),
+        Err(MinecraftError::UnloadedChunk(_))
+    ));
+    drop(deserializer);
+    computer.join().unwrap();
+}
@@ -5,11 +5,16 @@ macro_rules! block_enum {
         $($name:ident = $value:literal),*
     }) => {
         #[repr(u8)]
-        #[derive(Debug, Clone, PartialEq, Eq, Hash, num_enum::TryFromPrimitive)]
+        #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, num_enum::TryFromPrimitive)]
         pub enum MinecraftBlock {
             $($name,)*
         }
 
+        impl MinecraftBlock {
+            /// Every block, in the order of their discriminants.
+            pub const ALL: &[MinecraftBlock] = &[$(MinecraftBlock::$name,)*];
+        }
+
         impl std::fmt::Display for MinecraftBlock {
             fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
                 write!(f, "{}", match self {
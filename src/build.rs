@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Chunks, Position, layout::ORIGIN};
+
+/// The direction a layout's "north" points to in the world.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Facing {
+    #[default]
+    North,
+    East,
+    South,
+    West,
+}
+
+/// The dimension a build lives in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Dimension {
+    #[default]
+    Overworld,
+    Nether,
+    End,
+}
+
+impl Dimension {
+    /// The dimension's identifier, as used by `/execute in`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Dimension::Overworld => "minecraft:overworld",
+            Dimension::Nether => "minecraft:the_nether",
+            Dimension::End => "minecraft:the_end",
+        }
+    }
+}
+
+/// Whether a layout lies flat on the ground or stands up as a wall.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    #[default]
+    Horizontal,
+
+    /// The layout's north points upwards and its upwards points south.
+    Vertical,
+}
+
+/// Where and how a build is placed in the world.
+///
+/// The serializer and deserializer must use the same configuration, which lets many builds
+/// coexist at different places.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildConfig {
+    pub origin: Position,
+    pub facing: Facing,
+    pub mirror: bool,
+    pub dimension: Dimension,
+    pub orientation: Orientation,
+    pub chunks: Chunks,
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            origin: ORIGIN,
+            facing: Facing::default(),
+            mirror: false,
+            dimension: Dimension::default(),
+            orientation: Orientation::default(),
+            chunks: Chunks::default(),
+        }
+    }
+}
+
+impl BuildConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tells builds apart by where they start, for the computer to keep count of each one's
+    /// blocks separately.
+    pub fn key(&self) -> String {
+        let Position { x, y, z } = self.origin;
+        format!("{}@{x},{y},{z}", self.dimension.id())
+    }
+
+    /// Place the first block at `origin`.
+    pub fn with_origin(mut self, origin: Position) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Turn the layout so that its north points to `facing`.
+    pub fn with_facing(mut self, facing: Facing) -> Self {
+        self.facing = facing;
+        self
+    }
+
+    /// Swap the layout's east and west.
+    pub fn with_mirror(mut self, mirror: bool) -> Self {
+        self.mirror = mirror;
+        self
+    }
+
+    pub fn with_dimension(mut self, dimension: Dimension) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Choose how chunks that may not be loaded are dealt with, see [`Chunks`].
+    pub fn with_chunks(mut self, chunks: Chunks) -> Self {
+        self.chunks = chunks;
+        self
+    }
+
+    /// Turn a position relative to the origin of a layout into a position in the world.
+    pub fn place(&self, Position { x, y, z }: Position) -> Position {
+        let (y, z) = match self.orientation {
+            Orientation::Horizontal => (y, z),
+            Orientation::Vertical => (-z, y),
+        };
+
+        let x = if self.mirror { -x } else { x };
+
+        let (x, z) = match self.facing {
+            Facing::North => (x, z),
+            Facing::East => (-z, x),
+            Facing::South => (-x, -z),
+            Facing::West => (z, -x),
+        };
+
+        self.origin + Position::new(x, y, z)
+    }
+}
+
+#[test]
+fn place_test() {
+    use crate::{Layout, Line, mock};
+    use serde::{Deserialize, Serialize};
+
+    let forward = Line.position(1);
+    let origin = Position::new(100, 64, -20);
+    let build = BuildConfig::new().with_origin(origin);
+
+    assert_eq!(build.place(forward), origin + Position::new(0, 0, -1));
+    assert_eq!(
+        build.with_facing(Facing::East).place(forward),
+        origin + Position::new(1, 0, 0)
+    );
+    assert_eq!(
+        build.with_orientation(Orientation::Vertical).place(forward),
+        origin + Position::new(0, 1, 0)
+    );
+    assert_eq!(
+        build
+            .with_mirror(true)
+            .with_facing(Facing::South)
+            .place(Position::new(1, 0, 0)),
+        origin + Position::new(1, 0, 0)
+    );
+
+    let build = build
+        .with_facing(Facing::West)
+        .with_dimension(Dimension::Nether);
+    let value = vec!["nether".to_string()];
+    let placed = mock::place_with(
+        |s| s.with_build(build).with_layout(Line),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+    assert!(
+        placed
+            .iter()
+            .all(|(p, _)| p.z == origin.z && p.x <= origin.x)
+    );
+
+    let decoded = mock::read_with(
+        placed,
+        |d| d.with_build(build).with_layout(Line),
+        |d| Vec::<String>::deserialize(d),
+    )
+    .unwrap();
+    assert_eq!(value, decoded);
+}
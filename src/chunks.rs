@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Command, ForceloadAction, MinecraftError, MinecraftResult, Position};
+
+/// A 16×16 column of the world, by its chunk coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Chunk {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl Chunk {
+    pub const fn new(x: i32, z: i32) -> Self {
+        Self { x, z }
+    }
+
+    /// The chunk a block is in.
+    pub fn containing(position: Position) -> Self {
+        Self::new(position.x.div_euclid(16), position.z.div_euclid(16))
+    }
+}
+
+/// A rectangle of chunks, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkArea {
+    pub from: Chunk,
+    pub to: Chunk,
+}
+
+impl ChunkArea {
+    pub fn new(from: Chunk, to: Chunk) -> Self {
+        Self { from, to }
+    }
+
+    pub fn contains(&self, chunk: Chunk) -> bool {
+        let (x, z) = (
+            self.from.x.min(self.to.x)..=self.from.x.max(self.to.x),
+            self.from.z.min(self.to.z)..=self.from.z.max(self.to.z),
+        );
+        x.contains(&chunk.x) && z.contains(&chunk.z)
+    }
+}
+
+/// How a build deals with chunks that may not be loaded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Chunks {
+    /// Assume every chunk the build touches is loaded.
+    #[default]
+    Any,
+
+    /// Forceload the chunks around the part of the build being placed or read, and release
+    /// them afterwards.
+    Forceload,
+
+    /// Refuse to place or read blocks outside of an area that is known to be loaded.
+    Within(ChunkArea),
+}
+
+/// The chunks a session has forceloaded, most recently used last.
+#[derive(Debug, Default)]
+pub(crate) struct Forceloaded {
+    chunks: VecDeque<Chunk>,
+}
+
+impl Forceloaded {
+    /// How many chunks are kept loaded at once.
+    const WINDOW: usize = 4;
+
+    /// Check that a block at `position` can be reached under `chunks`, returning the commands
+    /// that make it so.
+    pub fn visit(&mut self, chunks: Chunks, position: Position) -> MinecraftResult<Vec<Command>> {
+        let chunk = Chunk::containing(position);
+        match chunks {
+            Chunks::Any => Ok(Vec::new()),
+            Chunks::Forceload => Ok(self.enter(chunk)),
+            Chunks::Within(area) if area.contains(chunk) => Ok(Vec::new()),
+            Chunks::Within(_) => Err(MinecraftError::OutsideChunks(position)),
+        }
+    }
+
+    /// Record a visit to `chunk`, returning the commands that keep it loaded.
+    fn enter(&mut self, chunk: Chunk) -> Vec<Command> {
+        if let Some(i) = self.chunks.iter().position(|&c| c == chunk) {
+            self.chunks.remove(i);
+            self.chunks.push_back(chunk);
+            return Vec::new();
+        }
+
+        let mut commands = vec![Self::command(ForceloadAction::Add, chunk)];
+        self.chunks.push_back(chunk);
+        if self.chunks.len() > Self::WINDOW
+            && let Some(oldest) = self.chunks.pop_front()
+        {
+            commands.push(Self::command(ForceloadAction::Remove, oldest));
+        }
+        commands
+    }
+
+    /// Release every chunk, returning the commands that do so.
+    pub fn release(&mut self) -> Vec<Command> {
+        self.chunks
+            .drain(..)
+            .map(|chunk| Self::command(ForceloadAction::Remove, chunk))
+            .collect()
+    }
+
+    fn command(action: ForceloadAction, chunk: Chunk) -> Command {
+        Command::Forceload {
+            action,
+            x: chunk.x * 16,
+            z: chunk.z * 16,
+        }
+    }
+}
+
+#[test]
+fn forceload_test() {
+    use crate::{BuildConfig, Line, mock};
+    use serde::{Deserialize, Serialize};
+
+    let build = BuildConfig::new()
+        .with_origin(Position::new(0, 64, 0))
+        .with_chunks(Chunks::Forceload);
+    let value = "a string long enough to cross into the next chunks".to_string();
+
+    let commands = mock::record_with(
+        |s| s.with_build(build).with_layout(Line),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+    let forceloads: Vec<_> = commands
+        .into_iter()
+        .filter_map(|c| match c {
+            Command::Forceload { action, x, z } => Some((action, x, z)),
+            _ => None,
+        })
+        .collect();
+
+    use ForceloadAction::{Add, Remove};
+    assert_eq!(forceloads[0], (Add, 0, 0));
+    assert_eq!(forceloads[1], (Add, 0, -16));
+    assert!(forceloads.ends_with(&[(Remove, 0, -96), (Remove, 0, -112)]));
+
+    let within = build.with_chunks(Chunks::Within(ChunkArea::new(
+        Chunk::new(0, 0),
+        Chunk::new(0, -1),
+    )));
+    assert!(matches!(
+        mock::serialize_with(
+            |s| s.with_build(within).with_layout(Line),
+            |s| value.serialize(s)
+        ),
+        Err(MinecraftError::OutsideChunks(p)) if p == Position::new(0, 64, -17)
+    ));
+
+    let placed = mock::place_with(|s| s.with_build(build), |s| value.serialize(s)).unwrap();
+    let elsewhere = BuildConfig::new().with_origin(Position::new(1000, 64, 1000));
+    assert!(matches!(
+        mock::read_with(placed, |d| d.with_build(elsewhere), |d| String::deserialize(d)),
+        Err(MinecraftError::UnloadedChunk(p)) if p == elsewhere.origin
+    ));
+}
@@ -0,0 +1,542 @@
+use serde::{
+    Deserializer,
+    de::{
+        DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess,
+        Visitor, value::MapDeserializer,
+    },
+    forward_to_deserialize_any,
+};
+
+use super::{MinecraftDeserializer, map::MCMapAccess};
+use crate::{
+    MinecraftBlock, MinecraftError, MinecraftResult, NumberMarker, Variant,
+    layout::{Container, Shape},
+    option_ser::tag,
+    value::Tagged,
+};
+
+/// A sequence that ends at `terminator`, or wherever the visitor stops when there is none.
+pub(super) struct TerminatedSeq<'a> {
+    de: &'a mut MinecraftDeserializer,
+    terminator: Option<MinecraftBlock>,
+    finished: bool,
+
+    /// Whether elements are reported to the layout, see [`TerminatedSeq::open`].
+    opened: bool,
+
+    /// Elements yielded so far, and how many of them make up one entry of the container.
+    elements: usize,
+    stride: usize,
+}
+
+impl<'a> TerminatedSeq<'a> {
+    pub fn new(de: &'a mut MinecraftDeserializer, terminator: Option<MinecraftBlock>) -> Self {
+        Self {
+            de,
+            terminator,
+            finished: false,
+            opened: false,
+            elements: 0,
+            stride: 1,
+        }
+    }
+
+    /// Report `container` to the layout, along with every entry from here on.
+    ///
+    /// Structs yield their fields as alternating keys and values, one entry per pair.
+    pub fn open(&mut self, container: Container) {
+        self.de.shape(Shape::Open(container));
+        self.opened = true;
+        self.elements = 0;
+        self.stride = match container {
+            Container::Map | Container::Struct | Container::StructVariant => 2,
+            _ => 1,
+        };
+    }
+
+    /// Consume the terminator if the visitor stopped before reaching it.
+    pub fn finish(self) -> MinecraftResult<()> {
+        let Some(terminator) = self.terminator else {
+            return Ok(());
+        };
+
+        if !self.finished {
+            if self.opened {
+                self.de.shape(Shape::Element);
+            }
+
+            let b = self.de.consume()?;
+            if b != terminator {
+                return Err(MinecraftError::UnexpectedBlock {
+                    expected: terminator,
+                    found: b,
+                });
+            }
+        }
+
+        if self.opened {
+            self.de.shape(Shape::Close);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, 'de> SeqAccess<'de> for TerminatedSeq<'a> {
+    type Error = MinecraftError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if self.opened && self.elements.is_multiple_of(self.stride) {
+            self.de.shape(Shape::Element);
+        }
+
+        if let Some(terminator) = &self.terminator
+            && self.de.peek()? == *terminator
+        {
+            self.de.consume()?;
+            self.finished = true;
+            return Ok(None);
+        }
+
+        let value = seed.deserialize(&mut *self.de)?;
+        self.elements += 1;
+        Ok(Some(value))
+    }
+}
+
+/// Presents a named construct to `Value` as a tuple variant of [`Tagged`].
+///
+/// Enum variants read their identity up front, since its shape depends on the [`EnumRepr`].
+///
+/// [`EnumRepr`]: crate::EnumRepr
+struct TaggedAccess<'a> {
+    tagged: Tagged,
+    variant: Option<Variant>,
+    seq: TerminatedSeq<'a>,
+
+    /// Header elements left before the payload, which is opened as `container`.
+    header: usize,
+    container: Option<Container>,
+}
+
+impl<'a, 'de> SeqAccess<'de> for TaggedAccess<'a> {
+    type Error = MinecraftError;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if let Some(variant) = self.variant.take() {
+            return variant_seed(seed, variant).map(Some);
+        }
+
+        match self.header.checked_sub(1) {
+            Some(header) => self.header = header,
+            None => {
+                if let Some(container) = self.container.take() {
+                    self.seq.open(container);
+                }
+            }
+        }
+        self.seq.next_element_seed(seed)
+    }
+}
+
+/// Hand a variant identity to `seed` as an index, a name, or a single `name => index` entry.
+fn variant_seed<'de, T>(seed: T, variant: Variant) -> MinecraftResult<T::Value>
+where
+    T: DeserializeSeed<'de>,
+{
+    match variant {
+        Variant::Index(index) => seed.deserialize(index.into_deserializer()),
+        Variant::Name(name) => seed.deserialize(name.into_deserializer()),
+        Variant::NameAndIndex(name, index) => {
+            seed.deserialize(MapDeserializer::new(std::iter::once((name, index))))
+        }
+    }
+}
+
+impl<'a, 'de> EnumAccess<'de> for TaggedAccess<'a> {
+    type Error = MinecraftError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let tag = IntoDeserializer::<MinecraftError>::into_deserializer(self.tagged as u32);
+        let tagged = seed.deserialize(tag)?;
+        Ok((tagged, self))
+    }
+}
+
+impl<'a, 'de> VariantAccess<'de> for TaggedAccess<'a> {
+    type Error = MinecraftError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Err(self.mismatch("unit variant"))
+    }
+
+    fn newtype_variant_seed<T>(self, _seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        Err(self.mismatch("newtype variant"))
+    }
+
+    fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let value = visitor.visit_seq(&mut self)?;
+        self.seq.finish()?;
+        Ok(value)
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        Err(self.mismatch("struct variant"))
+    }
+}
+
+impl<'a> TaggedAccess<'a> {
+    fn mismatch(&self, expected: &str) -> MinecraftError {
+        MinecraftError::TypeMismatch {
+            expected: expected.to_string(),
+            found: format!("{:?}", self.tagged),
+        }
+    }
+}
+
+impl MinecraftDeserializer {
+    /// Visit the elements of a sequence-like `container` whose header has been read.
+    pub(super) fn visit_elements<'de, V>(
+        &mut self,
+        container: Container,
+        visitor: V,
+    ) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut seq = TerminatedSeq::new(self, Some(MinecraftBlock::DarkPrismarine));
+        seq.open(container);
+        let value = visitor.visit_seq(&mut seq)?;
+        seq.finish()?;
+        Ok(value)
+    }
+
+    /// Describe the next value to `visitor`, keeping every name and index the blocks carry.
+    pub(super) fn deserialize_value<'de, V>(&mut self, visitor: V) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(tag) = self.pending_tag.take() {
+            return self.deserialize_announced(tag, visitor, true);
+        }
+
+        let (tagged, terminator) = match self.peek()? {
+            MinecraftBlock::SpruceLog => (Tagged::NewtypeStruct, None),
+            MinecraftBlock::CrimsonStem => (Tagged::Tuple, Some(MinecraftBlock::DarkPrismarine)),
+            MinecraftBlock::WarpedStem => {
+                (Tagged::TupleStruct, Some(MinecraftBlock::DarkPrismarine))
+            }
+            MinecraftBlock::GoldBlock => (Tagged::Struct, Some(MinecraftBlock::EmeraldBlock)),
+            MinecraftBlock::OakLog => (Tagged::UnitVariant, None),
+            MinecraftBlock::DarkOakLog => (Tagged::NewtypeVariant, None),
+            MinecraftBlock::PurpurBlock => {
+                (Tagged::TupleVariant, Some(MinecraftBlock::DarkPrismarine))
+            }
+            MinecraftBlock::DiamondBlock => {
+                (Tagged::StructVariant, Some(MinecraftBlock::EmeraldBlock))
+            }
+            _ => return self.deserialize_plain(visitor),
+        };
+
+        self.consume()?;
+        let variant = match tagged {
+            Tagged::UnitVariant
+            | Tagged::NewtypeVariant
+            | Tagged::TupleVariant
+            | Tagged::StructVariant => Some(self.parse_variant()?),
+            _ => None,
+        };
+
+        let (header, container) = match tagged {
+            Tagged::Tuple => (0, Some(Container::Tuple)),
+            Tagged::TupleStruct => (2, Some(Container::TupleStruct)),
+            Tagged::TupleVariant => (1, Some(Container::TupleVariant)),
+            Tagged::Struct => (2, Some(Container::Struct)),
+            Tagged::StructVariant => (1, Some(Container::StructVariant)),
+            _ => (0, None),
+        };
+
+        visitor.visit_enum(TaggedAccess {
+            tagged,
+            variant,
+            seq: TerminatedSeq::new(self, terminator),
+            header,
+            container,
+        })
+    }
+
+    /// Describe the next value to `visitor` using only the shapes other formats understand.
+    ///
+    /// Structs become maps and enum variants become single-entry maps keyed by variant index.
+    pub(super) fn deserialize_described<'de, V>(&mut self, visitor: V) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if let Some(tag) = self.pending_tag.take() {
+            return self.deserialize_announced(tag, visitor, false);
+        }
+
+        match self.peek()? {
+            MinecraftBlock::SpruceLog => {
+                self.consume()?;
+                self.parse_string()?;
+                visitor.visit_newtype_struct(self)
+            }
+            MinecraftBlock::CrimsonStem => {
+                self.consume()?;
+                self.visit_elements(Container::Tuple, visitor)
+            }
+            MinecraftBlock::WarpedStem => {
+                self.consume()?;
+                self.parse_string()?;
+                self.parse_a_number()?;
+                self.visit_elements(Container::TupleStruct, visitor)
+            }
+            MinecraftBlock::GoldBlock => {
+                self.consume()?;
+                self.parse_string()?;
+                self.parse_a_number()?;
+                visitor.visit_map(MCMapAccess::new(self, Container::Struct))
+            }
+            b @ (MinecraftBlock::OakLog
+            | MinecraftBlock::DarkOakLog
+            | MinecraftBlock::PurpurBlock
+            | MinecraftBlock::DiamondBlock) => {
+                self.consume()?;
+                let variant = self.parse_variant()?;
+                visitor.visit_map(VariantMap {
+                    de: self,
+                    marker: b,
+                    variant: Some(variant),
+                })
+            }
+            _ => self.deserialize_plain(visitor),
+        }
+    }
+
+    /// Describe the payload of a `Some` using the type its tag announced.
+    ///
+    /// Primitives are decoded exactly as tagged, so a `u8` stays a `u8` instead of whatever
+    /// width its marker blocks suggest. Containers must start with the marker their tag implies.
+    fn deserialize_announced<'de, V>(
+        &mut self,
+        announced: u8,
+        visitor: V,
+        tagged: bool,
+    ) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let marker = match announced {
+            tag::BOOL => return self.deserialize_bool(visitor),
+            tag::I8 => return self.deserialize_i8(visitor),
+            tag::I16 => return self.deserialize_i16(visitor),
+            tag::I32 => return self.deserialize_i32(visitor),
+            tag::I64 => return self.deserialize_i64(visitor),
+            tag::U8 => return self.deserialize_u8(visitor),
+            tag::U16 => return self.deserialize_u16(visitor),
+            tag::U32 => return self.deserialize_u32(visitor),
+            tag::U64 => return self.deserialize_u64(visitor),
+            tag::F32 => return self.deserialize_f32(visitor),
+            tag::F64 => return self.deserialize_f64(visitor),
+            tag::CHAR => return self.deserialize_char(visitor),
+            tag::STR => return self.deserialize_string(visitor),
+            tag::BYTES => return self.deserialize_byte_buf(visitor),
+            tag::NONE | tag::SOME => return self.deserialize_option(visitor),
+            tag::UNIT | tag::UNIT_STRUCT => return self.deserialize_unit(visitor),
+            tag::UNIT_VARIANT => MinecraftBlock::OakLog,
+            tag::NEWTYPE_STRUCT => MinecraftBlock::SpruceLog,
+            tag::NEWTYPE_VARIANT => MinecraftBlock::DarkOakLog,
+            tag::SEQ => MinecraftBlock::CherryLog,
+            tag::TUPLE => MinecraftBlock::CrimsonStem,
+            tag::TUPLE_STRUCT => MinecraftBlock::WarpedStem,
+            tag::TUPLE_VARIANT => MinecraftBlock::PurpurBlock,
+            tag::MAP => MinecraftBlock::PurpurPillar,
+            tag::STRUCT => MinecraftBlock::GoldBlock,
+            _ => MinecraftBlock::DiamondBlock,
+        };
+
+        let found = self.peek()?;
+        if found != marker {
+            return Err(MinecraftError::TypeMismatch {
+                expected: tag::NAMES[announced as usize].to_string(),
+                found: found.to_string(),
+            });
+        }
+
+        if tagged {
+            self.deserialize_value(visitor)
+        } else {
+            self.deserialize_described(visitor)
+        }
+    }
+
+    /// Describe values that any visitor understands: primitives, options, sequences and maps.
+    fn deserialize_plain<'de, V>(&mut self, visitor: V) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            MinecraftBlock::RedstoneBlock => {
+                self.consume()?;
+                visitor.visit_bool(true)
+            }
+            MinecraftBlock::RedstoneLamp => {
+                self.consume()?;
+                visitor.visit_bool(false)
+            }
+            b if NumberMarker::is_marker(&b) => self.visit_number(visitor),
+            MinecraftBlock::GildedBlackstone => visitor.visit_string(self.parse_string()?),
+            MinecraftBlock::Blackstone => {
+                visitor.visit_byte_buf(self.parse_bytes(MinecraftBlock::Blackstone)?)
+            }
+            MinecraftBlock::CoalBlock => {
+                self.consume()?;
+                match self.consume()? {
+                    MinecraftBlock::CoalBlock => visitor.visit_none(),
+                    b => {
+                        self.pending_tag = Some(tag::from_block(b)?);
+                        visitor.visit_some(self)
+                    }
+                }
+            }
+            MinecraftBlock::Bedrock => {
+                self.consume()?;
+                visitor.visit_unit()
+            }
+            MinecraftBlock::CherryLog => {
+                self.consume()?;
+                self.visit_elements(Container::Seq, visitor)
+            }
+            MinecraftBlock::PurpurPillar => {
+                self.consume()?;
+                visitor.visit_map(MCMapAccess::new(self, Container::Map))
+            }
+            b => Err(MinecraftError::AnUnexpectedBlock(b)),
+        }
+    }
+
+    /// Visit a number with the width and signedness recorded by its marker blocks.
+    fn visit_number<'de, V>(&mut self, visitor: V) -> MinecraftResult<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (marker, signed, v) = self.parse_a_number()?;
+
+        match (marker, signed) {
+            (MinecraftBlock::EndStone, false) => visitor.visit_u8(v as u8),
+            (MinecraftBlock::EndStone, true) => visitor.visit_i8(v as u8 as i8),
+            (MinecraftBlock::RawIronBlock, false) => visitor.visit_u16(v as u16),
+            (MinecraftBlock::RawIronBlock, true) => visitor.visit_i16(v as u16 as i16),
+            (MinecraftBlock::RawCopperBlock, false) => visitor.visit_u32(v as u32),
+            (MinecraftBlock::RawCopperBlock, true) => visitor.visit_i32(v as u32 as i32),
+            (MinecraftBlock::RawGoldBlock, false) => visitor.visit_u64(v as u64),
+            (MinecraftBlock::RawGoldBlock, true) => visitor.visit_i64(v as u64 as i64),
+            (MinecraftBlock::Shroomlight, _) => visitor.visit_f32(f32::from_bits(v as u32)),
+            (MinecraftBlock::Glowstone, _) => visitor.visit_f64(f64::from_bits(v as u64)),
+            (MinecraftBlock::ChiseledDeepslate, _) => {
+                let c = char::from_u32(v as u32).ok_or_else(|| {
+                    MinecraftError::Custom("Could not convert u32 to char".to_string())
+                })?;
+                visitor.visit_char(c)
+            }
+            (b, _) => Err(MinecraftError::AnUnexpectedBlock(b)),
+        }
+    }
+}
+
+/// Presents an enum variant as a map with a single `variant => payload` entry.
+///
+/// The key is the variant name when the build carries one, and its index otherwise.
+struct VariantMap<'a> {
+    de: &'a mut MinecraftDeserializer,
+    marker: MinecraftBlock,
+    variant: Option<Variant>,
+}
+
+impl<'a, 'de> MapAccess<'de> for VariantMap<'a> {
+    type Error = MinecraftError;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.variant.take() {
+            Some(Variant::Index(index)) => seed.deserialize(index.into_deserializer()).map(Some),
+            Some(Variant::Name(name) | Variant::NameAndIndex(name, _)) => {
+                seed.deserialize(name.into_deserializer()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(VariantPayload {
+            de: &mut *self.de,
+            marker: self.marker.clone(),
+        })
+    }
+}
+
+/// The contents of an enum variant, whose shape is decided by the variant's marker block.
+struct VariantPayload<'a> {
+    de: &'a mut MinecraftDeserializer,
+    marker: MinecraftBlock,
+}
+
+impl<'a, 'de> serde::Deserializer<'de> for VariantPayload<'a> {
+    type Error = MinecraftError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.marker {
+            MinecraftBlock::OakLog => visitor.visit_unit(),
+            MinecraftBlock::DarkOakLog => self.de.deserialize_described(visitor),
+            MinecraftBlock::PurpurBlock => {
+                self.de.parse_a_number()?;
+                self.de.visit_elements(Container::TupleVariant, visitor)
+            }
+            _ => {
+                self.de.parse_a_number()?;
+                visitor.visit_map(MCMapAccess::new(self.de, Container::StructVariant))
+            }
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
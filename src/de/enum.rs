@@ -3,7 +3,7 @@ use serde::{
     de::{EnumAccess, VariantAccess, Visitor},
 };
 
-use crate::{MinecraftBlock, MinecraftError};
+use crate::{EnumRepr, MinecraftBlock, MinecraftError, layout::Container};
 
 use super::MinecraftDeserializer;
 
@@ -25,8 +25,11 @@ impl<'de, 'a> EnumAccess<'de> for MCEnumAccessor<'a> {
     where
         V: serde::de::DeserializeSeed<'de>,
     {
-        let variant_index = seed.deserialize(&mut *self.de)?;
-        Ok((variant_index, self))
+        let variant = seed.deserialize(&mut *self.de)?;
+        if self.de.enum_repr == EnumRepr::NameAndIndex {
+            self.de.parse_number(MinecraftBlock::RawCopperBlock, None)?;
+        }
+        Ok((variant, self))
     }
 }
 
@@ -61,17 +64,7 @@ impl<'de, 'a> VariantAccess<'de> for MCEnumAccessor<'a> {
 
         let _serialized_len = self.de.deserialize_u32(LengthVisitor)?;
 
-        let result = visitor.visit_seq(&mut *self.de)?;
-
-        let end_block = self.de.consume()?;
-        if end_block != MinecraftBlock::DarkPrismarine {
-            return Err(MinecraftError::UnexpectedBlock {
-                expected: MinecraftBlock::DarkPrismarine,
-                found: end_block,
-            });
-        }
-
-        Ok(result)
+        self.de.visit_elements(Container::TupleVariant, visitor)
     }
 
     fn struct_variant<V>(
@@ -97,9 +90,53 @@ impl<'de, 'a> VariantAccess<'de> for MCEnumAccessor<'a> {
 
         let result = visitor.visit_map(&mut crate::de::map::MCMapAccess::new(
             self.de,
-            MinecraftBlock::EmeraldBlock,
+            Container::StructVariant,
         ))?;
 
         Ok(result)
     }
 }
+
+#[test]
+fn enum_by_name_test() {
+    use crate::mock;
+
+    mod old {
+        #[derive(serde::Serialize)]
+        pub enum Tool {
+            Pick(u8),
+            Axe { durability: u16 },
+            Hoe,
+        }
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    enum Tool {
+        Shovel,
+        Hoe,
+        Axe { durability: u16 },
+        Pick(u8),
+    }
+
+    let tools = vec![
+        old::Tool::Pick(3),
+        old::Tool::Axe { durability: 250 },
+        old::Tool::Hoe,
+    ];
+    let expected = vec![Tool::Pick(3), Tool::Axe { durability: 250 }, Tool::Hoe];
+
+    for repr in [EnumRepr::Name, EnumRepr::NameAndIndex] {
+        let blocks = mock::serialize_with(
+            |s| s.with_enum_repr(repr),
+            |s| serde::Serialize::serialize(&tools, s),
+        )
+        .unwrap();
+        let decoded: Vec<Tool> = mock::deserialize_with(
+            blocks,
+            |d| d.with_enum_repr(repr),
+            |d| serde::Deserialize::deserialize(d),
+        )
+        .unwrap();
+        assert_eq!(decoded, expected);
+    }
+}
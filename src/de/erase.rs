@@ -0,0 +1,148 @@
+use serde::{Deserialize, de::IgnoredAny};
+
+use super::MinecraftDeserializer;
+use crate::{Capability, Chunk, Command, MinecraftError, MinecraftResult, Position, Region, Reply};
+
+const AIR: &str = "minecraft:air";
+
+impl MinecraftDeserializer {
+    /// Clear a build back to air, returning how many blocks it was made of.
+    ///
+    /// Without a `len`, the build is read up to its end marker first, which works for every
+    /// layout. With a recorded `len`, the first `len` positions of the layout are cleared without
+    /// reading anything, which also clears a build that was never finished but only suits layouts
+    /// that do not follow the shape of the value.
+    ///
+    /// Straight runs of blocks are cleared with a single `/fill` if the computer supports it.
+    pub fn erase(&mut self, len: Option<usize>) -> MinecraftResult<usize> {
+        self.clear(len, None)
+    }
+
+    /// Clear the build in `region`, by its recorded length unless the layout follows the shape
+    /// of the value, failing before anything is cleared if a block lies outside the region.
+    pub(crate) fn erase_region(&mut self, region: &Region) -> MinecraftResult<usize> {
+        let len = (!self.layout.follows_shape()).then_some(region.len);
+        self.clear(len, Some(region))
+    }
+
+    fn clear(&mut self, len: Option<usize>, region: Option<&Region>) -> MinecraftResult<usize> {
+        let len = match len {
+            Some(len) => len,
+            None => {
+                IgnoredAny::deserialize(&mut *self)?;
+                self.index
+            }
+        };
+
+        let positions: Vec<_> = (0..len)
+            .map(|index| self.build.place(self.layout.position(index)))
+            .collect();
+
+        if let Some(region) = region
+            && let Some(&position) = positions.iter().find(|&&p| !region.contains(p))
+        {
+            return Err(MinecraftError::OutsideRegion {
+                name: region.name.clone(),
+                position,
+            });
+        }
+
+        let runs = match self.supports(Capability::Fill) {
+            true => runs(&positions),
+            false => positions.iter().map(|&p| (p, p)).collect(),
+        };
+
+        for (first, last) in runs {
+            self.prepare(first)?;
+            let block = AIR.to_string();
+            let command = match first == last {
+                true => Command::Place {
+                    position: first,
+                    block,
+                },
+                false => Command::Fill {
+                    from: first,
+                    to: last,
+                    block,
+                },
+            };
+
+            match self.call(command)? {
+                Reply::Done | Reply::Placed { .. } => {}
+                Reply::Failed { message } => {
+                    return Err(MinecraftError::PlacementFailed {
+                        position: first,
+                        message,
+                    });
+                }
+                reply => return Err(MinecraftError::UnexpectedReply(reply)),
+            }
+        }
+        Ok(len)
+    }
+}
+
+/// Split `positions` into straight lines of neighbouring blocks that stay within one chunk, as
+/// their first and last positions.
+fn runs(positions: &[Position]) -> Vec<(Position, Position)> {
+    let mut runs: Vec<(Position, Position)> = Vec::new();
+    let mut step = None;
+
+    for &position in positions {
+        if let Some((first, last)) = runs.last_mut() {
+            let delta = (
+                position.x - last.x,
+                position.y - last.y,
+                position.z - last.z,
+            );
+            let neighbour = delta.0.abs() + delta.1.abs() + delta.2.abs() == 1;
+
+            if neighbour
+                && step.is_none_or(|step| step == delta)
+                && Chunk::containing(*first) == Chunk::containing(position)
+            {
+                *last = position;
+                step = Some(delta);
+                continue;
+            }
+        }
+
+        runs.push((position, position));
+        step = None;
+    }
+    runs
+}
+
+#[test]
+fn erase_test() {
+    use crate::{BuildConfig, Layout, Line, MinecraftBlock, mock};
+    use serde::Serialize;
+
+    let value = vec!["erase".to_string(), "me".to_string()];
+    let placed = mock::place_with(|s| s, |s| value.serialize(s)).unwrap();
+    let (len, world) = mock::read_in(placed.clone(), |d| d, |d| d.erase(None)).unwrap();
+    assert_eq!(len, placed.len());
+    assert!(world.is_empty());
+
+    // An unfinished build along a line, next to something that must survive.
+    let build = BuildConfig::new().with_origin(Position::new(0, 64, -1));
+    let line: Vec<_> = (0..20)
+        .map(|i| (build.place(Line.position(i)), MinecraftBlock::Stone))
+        .collect();
+    let (_, world) = mock::read_in(
+        line,
+        |d| d.with_build(build).with_layout(Line),
+        |d| d.erase(Some(18)),
+    )
+    .unwrap();
+    assert_eq!(world.len(), 2);
+
+    let positions: Vec<_> = (0..20).map(|i| build.place(Line.position(i))).collect();
+    assert_eq!(
+        runs(&positions),
+        [
+            (positions[0], positions[15]),
+            (positions[16], positions[19])
+        ]
+    );
+}
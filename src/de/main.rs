@@ -1,70 +1,189 @@
-use std::net::TcpStream;
-
-use tungstenite::WebSocket;
-
 use crate::{
-    NumberMarker,
-    blocks::MinecraftBlock,
+    BuildConfig, Capability, Command, EnumRepr, NumberMarker, Palette, Peer, Reply, Role,
+    Transport, Variant,
+    blocks::{BASE, MinecraftBlock},
+    chunks::Forceloaded,
     de::map::MCMapAccess,
+    layout::{Container, Layout, Position, Shape, Spiral},
+    option_ser::tag,
+    protocol::Channel,
     result::{MinecraftError, MinecraftResult},
+    value,
 };
 
 use super::r#enum::MCEnumAccessor;
 
 pub struct MinecraftDeserializer {
-    socket: WebSocket<TcpStream>,
+    channel: Channel,
     next: Option<MinecraftBlock>,
+    pub(super) layout: Box<dyn Layout>,
+    pub(super) build: BuildConfig,
+
+    /// Whether the computer has been told about `build` yet.
+    configured: bool,
+    forceloaded: Forceloaded,
+
+    /// Index of the next block to read.
+    pub(super) index: usize,
+    pub(super) enum_repr: EnumRepr,
+
+    palette: Palette,
+
+    /// The type tag written by an enclosing `Some`, checked by whichever method decodes it.
+    pub(super) pending_tag: Option<u8>,
 }
 
 impl MinecraftDeserializer {
-    pub fn new(socket: WebSocket<TcpStream>) -> Self {
-        MinecraftDeserializer { socket, next: None }
+    /// Read blocks through `transport`, usually a websocket a computer has opened.
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        MinecraftDeserializer {
+            channel: Channel::new(transport),
+            next: None,
+            layout: Box::new(Spiral),
+            build: BuildConfig::default(),
+            configured: false,
+            forceloaded: Forceloaded::default(),
+            index: 0,
+            enum_repr: EnumRepr::default(),
+            pending_tag: None,
+            palette: Palette::default(),
+        }
+    }
+
+    /// Connect to a deserializing computer waiting at `url` (`ws://` or `wss://`), such as one
+    /// behind a relay, and go through the handshake with it, refusing it unless it presents
+    /// `token` when one is given.
+    pub fn connect(url: &str, token: Option<&str>) -> MinecraftResult<Self> {
+        let (socket, peer) = crate::transport::connect(url, Role::De, token)?;
+        Ok(Self::new(socket).with_peer(&peer))
+    }
+
+    /// Choose where blocks are read from; this must match the serializer's [`Layout`].
+    pub fn with_layout<L: Layout + 'static>(mut self, layout: L) -> Self {
+        self.layout = Box::new(layout);
+        self
+    }
+
+    pub(crate) fn with_boxed_layout(mut self, layout: Box<dyn Layout>) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Only use what a computer that completed the [`crate::handshake`] supports.
+    pub fn with_peer(mut self, peer: &Peer) -> Self {
+        self.channel.set_peer(peer);
+        self
+    }
+
+    /// The computer reading the blocks, if it went through the [`crate::handshake`].
+    pub fn peer(&self) -> Option<&Peer> {
+        self.channel.peer()
+    }
+
+    /// Whether the computer supports `capability`.
+    pub(super) fn supports(&self, capability: Capability) -> bool {
+        self.channel.supports(capability)
+    }
+
+    /// Choose where the build is read from; this must match the serializer's [`BuildConfig`].
+    pub fn with_build(mut self, build: BuildConfig) -> Self {
+        self.build = build;
+        self
+    }
+
+    /// The position the next block will be read from.
+    pub fn position(&self) -> Position {
+        self.build.place(self.layout.position(self.index))
+    }
+
+    /// Read the blocks of `palette` instead of the default ones; this must match the serializer's.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Choose how enum variants are identified; this must match the serializer's [`EnumRepr`].
+    pub fn with_enum_repr(mut self, enum_repr: EnumRepr) -> Self {
+        self.enum_repr = enum_repr;
+        self
+    }
+
+    /// Check the tag announced by an enclosing `Some` against the kinds the caller can decode.
+    pub(super) fn check_tag(&mut self, accepted: &[u8]) -> MinecraftResult<()> {
+        match self.pending_tag.take() {
+            Some(found) if !accepted.contains(&found) => Err(MinecraftError::TypeMismatch {
+                expected: accepted
+                    .iter()
+                    .map(|&t| tag::NAMES[t as usize])
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                found: tag::NAMES[found as usize].to_string(),
+            }),
+            _ => Ok(()),
+        }
     }
 
     pub(super) fn peek(&mut self) -> MinecraftResult<MinecraftBlock> {
-        let block = self.consume()?;
-        self.rewind()?;
+        if let Some(next) = &self.next {
+            return Ok(next.clone());
+        }
 
+        let block = self.read(self.position())?;
         let _ = self.next.replace(block.clone());
         Ok(block)
     }
 
     pub(super) fn consume(&mut self) -> MinecraftResult<MinecraftBlock> {
-        if let Some(next) = self.next.take() {
-            self.skip()?;
-            return Ok(next);
-        }
+        let block = match self.next.take() {
+            Some(next) => next,
+            None => self.read(self.position())?,
+        };
 
-        self.socket
-            .write(tungstenite::Message::Text("consume".into()))?;
-        self.socket.flush()?;
+        self.index += 1;
+        Ok(block)
+    }
 
-        let response = self.socket.read()?;
-        let text = response.to_text()?;
+    /// Report a step of the serde walk to the layout, exactly as the serializer did.
+    pub(super) fn shape(&mut self, shape: Shape) {
+        self.layout.shape(self.index, shape);
 
-        text.try_into()
+        // The layout may have moved the block that was peeked.
+        self.next = None;
     }
 
-    pub(super) fn rewind(&mut self) -> MinecraftResult<()> {
-        self.socket
-            .write(tungstenite::Message::Text("rewind".into()))?;
-        self.socket.flush()?;
-
-        let response = self.socket.read()?;
-        let text = response.to_text()?;
-        match text == "done" {
-            true => Ok(()),
-            false => Err(MinecraftError::RewindFailed),
+    /// Ask the computer for the block at `position`.
+    fn read(&mut self, position: Position) -> MinecraftResult<MinecraftBlock> {
+        self.prepare(position)?;
+        match self.call(Command::Read { position })? {
+            Reply::Block { name } => self.palette.block(&name),
+            Reply::Unloaded => Err(MinecraftError::UnloadedChunk(position)),
+            reply => Err(MinecraftError::UnexpectedReply(reply)),
         }
     }
 
-    pub(super) fn skip(&mut self) -> MinecraftResult<()> {
-        self.socket
-            .write(tungstenite::Message::Text("skip".into()))?;
-        self.socket.flush()?;
+    /// Make sure the computer can reach `position`, telling it which dimension to work in first.
+    pub(super) fn prepare(&mut self, position: Position) -> MinecraftResult<()> {
+        if !self.configured {
+            self.configured = true;
+            self.channel.require(&Capability::required_by(self.build))?;
+
+            if self.channel.supports(Capability::Dimension) {
+                let id = self.build.dimension.id().to_string();
+                self.call(Command::Dimension { id })?;
+            }
+        }
+
+        for command in self.forceloaded.visit(self.build.chunks, position)? {
+            self.call(command)?;
+        }
         Ok(())
     }
 
+    /// Send a command to the computer and return its reply.
+    pub(super) fn call(&mut self, command: Command) -> MinecraftResult<Reply> {
+        self.channel.call(self.index, command)
+    }
+
     pub(super) fn parse_number(
         &mut self,
         marker_block: MinecraftBlock,
@@ -96,14 +215,18 @@ impl MinecraftDeserializer {
             }
 
             let bit = block.block_to_bit()? as u128;
-            result *= 75;
+            result *= BASE as u128;
             result += bit;
         }
 
         Ok(result)
     }
 
-    fn parse_a_number(&mut self) -> Result<u128, MinecraftError> {
+    /// Parse a number of any width, returning its marker block, whether it carried a sign block
+    /// and its raw value.
+    pub(super) fn parse_a_number(
+        &mut self,
+    ) -> Result<(MinecraftBlock, bool, u128), MinecraftError> {
         let marker = self.consume()?;
         if !NumberMarker::is_marker(&marker) {
             return Err(MinecraftError::Custom(
@@ -113,9 +236,10 @@ impl MinecraftDeserializer {
 
         let mut result = 0;
 
-        let sign = self.consume()?;
-        if NumberMarker::is_sign_marker(&sign) {
-            result += sign.block_to_bit()? as u128;
+        let first = self.consume()?;
+        let signed = NumberMarker::is_sign_marker(&first);
+        if !signed {
+            result = first.block_to_bit()? as u128;
         }
 
         loop {
@@ -125,14 +249,17 @@ impl MinecraftDeserializer {
             }
 
             let bit = block.block_to_bit()? as u128;
-            result *= 75;
+            result *= BASE as u128;
             result += bit;
         }
 
-        Ok(result)
+        Ok((marker, signed, result))
     }
 
-    fn parse_bytes(&mut self, marker_block: MinecraftBlock) -> Result<Vec<u8>, MinecraftError> {
+    pub(super) fn parse_bytes(
+        &mut self,
+        marker_block: MinecraftBlock,
+    ) -> Result<Vec<u8>, MinecraftError> {
         let b = self.consume()?;
         if b != marker_block {
             return Err(MinecraftError::UnexpectedBlock {
@@ -164,27 +291,52 @@ impl MinecraftDeserializer {
         Ok(bytes)
     }
 
-    fn parse_string(&mut self) -> Result<String, MinecraftError> {
+    /// Read the identity of an enum variant as configured by [`EnumRepr`].
+    pub(super) fn parse_variant(&mut self) -> MinecraftResult<Variant> {
+        let index = |de: &mut Self| de.parse_number(MinecraftBlock::RawCopperBlock, None);
+
+        Ok(match self.enum_repr {
+            EnumRepr::Index => Variant::Index(index(self)? as u32),
+            EnumRepr::Name => Variant::Name(self.parse_string()?),
+            EnumRepr::NameAndIndex => {
+                let name = self.parse_string()?;
+                Variant::NameAndIndex(name, index(self)? as u32)
+            }
+        })
+    }
+
+    pub(super) fn parse_string(&mut self) -> Result<String, MinecraftError> {
         let bytes = self.parse_bytes(MinecraftBlock::GildedBlackstone)?;
         let string = String::from_utf8(bytes)?;
         Ok(string)
     }
 }
 
+impl Drop for MinecraftDeserializer {
+    fn drop(&mut self) {
+        for command in self.forceloaded.release() {
+            let _ = self.call(command);
+        }
+    }
+}
+
 impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     type Error = MinecraftError;
 
-    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+    #[inline(always)]
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        unimplemented!()
+        self.deserialize_described(visitor)
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::BOOL])?;
+
         match self.consume()? {
             MinecraftBlock::RedstoneBlock => visitor.visit_bool(true),
             MinecraftBlock::RedstoneLamp => visitor.visit_bool(false),
@@ -197,6 +349,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::I8])?;
+
         let number = self.parse_number(
             MinecraftBlock::EndStone,
             Some(MinecraftBlock::OchreFroglight),
@@ -209,6 +363,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::I16])?;
+
         let number = self.parse_number(
             MinecraftBlock::RawIronBlock,
             Some(MinecraftBlock::VerdantFroglight),
@@ -221,6 +377,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::I32])?;
+
         let number = self.parse_number(
             MinecraftBlock::RawCopperBlock,
             Some(MinecraftBlock::PearlescentFroglight),
@@ -233,6 +391,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::I64])?;
+
         let number = self.parse_number(
             MinecraftBlock::RawGoldBlock,
             Some(MinecraftBlock::SeaLantern),
@@ -245,6 +405,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::U8])?;
+
         let number = self.parse_number(MinecraftBlock::EndStone, None)?;
         visitor.visit_u8(number as u8)
     }
@@ -254,6 +416,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::U16])?;
+
         let number = self.parse_number(MinecraftBlock::RawIronBlock, None)?;
         visitor.visit_u16(number as u16)
     }
@@ -263,6 +427,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::U32])?;
+
         let number = self.parse_number(MinecraftBlock::RawCopperBlock, None)?;
         visitor.visit_u32(number as u32)
     }
@@ -272,6 +438,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::U64])?;
+
         let number = self.parse_number(MinecraftBlock::RawGoldBlock, None)?;
         visitor.visit_u64(number as u64)
     }
@@ -281,6 +449,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::F32])?;
+
         let bits = self.parse_number(MinecraftBlock::Shroomlight, None)? as u32;
         let v = f32::from_bits(bits);
         visitor.visit_f32(v)
@@ -291,6 +461,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::F64])?;
+
         let bits = self.parse_number(MinecraftBlock::Glowstone, None)? as u64;
         let v = f64::from_bits(bits);
         visitor.visit_f64(v)
@@ -301,6 +473,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::CHAR])?;
+
         let raw = self.parse_number(MinecraftBlock::ChiseledDeepslate, None)? as u32;
         let c = char::from_u32(raw)
             .ok_or_else(|| MinecraftError::Custom("Could not convert u32 to char".to_string()))?;
@@ -320,6 +494,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::STR])?;
+
         let string = self.parse_string()?;
         visitor.visit_string(string)
     }
@@ -337,6 +513,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::BYTES])?;
+
         let bytes = self.parse_bytes(MinecraftBlock::Blackstone)?;
         visitor.visit_byte_buf(bytes)
     }
@@ -345,6 +523,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::NONE, tag::SOME])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::CoalBlock {
             return Err(MinecraftError::UnexpectedBlock {
@@ -357,6 +537,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
         if b == MinecraftBlock::CoalBlock {
             return visitor.visit_none();
         }
+
+        self.pending_tag = Some(tag::from_block(b)?);
         visitor.visit_some(self)
     }
 
@@ -364,6 +546,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::UNIT, tag::UNIT_STRUCT])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::Bedrock {
             return Err(MinecraftError::UnexpectedBlock {
@@ -394,6 +578,12 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        if name == value::TOKEN {
+            return self.deserialize_value(visitor);
+        }
+
+        self.check_tag(&[tag::NEWTYPE_STRUCT])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::SpruceLog {
             return Err(MinecraftError::UnexpectedBlock {
@@ -412,6 +602,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::SEQ])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::CherryLog {
             return Err(MinecraftError::UnexpectedBlock {
@@ -420,22 +612,15 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
             });
         }
 
-        let seq = visitor.visit_seq(&mut *self);
-
-        let b = self.consume()?;
-        match b {
-            MinecraftBlock::DarkPrismarine => seq,
-            _ => Err(MinecraftError::UnexpectedBlock {
-                expected: MinecraftBlock::DarkPrismarine,
-                found: b,
-            }),
-        }
+        self.visit_elements(Container::Seq, visitor)
     }
 
     fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::TUPLE])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::CrimsonStem {
             return Err(MinecraftError::UnexpectedBlock {
@@ -444,16 +629,7 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
             });
         }
 
-        let tuple = visitor.visit_seq(&mut *self);
-
-        let b = self.consume()?;
-        match b {
-            MinecraftBlock::DarkPrismarine => tuple,
-            _ => Err(MinecraftError::UnexpectedBlock {
-                expected: MinecraftBlock::DarkPrismarine,
-                found: b,
-            }),
-        }
+        self.visit_elements(Container::Tuple, visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -465,6 +641,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::TUPLE_STRUCT])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::WarpedStem {
             return Err(MinecraftError::UnexpectedBlock {
@@ -476,25 +654,18 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
         let actual_name = self.parse_string()?;
         assert_eq!(name, actual_name);
 
-        let actual_len = self.parse_a_number()? as usize;
-        assert_eq!(actual_len, len);
-
-        let tuple_struct = visitor.visit_seq(&mut *self);
+        let (_, _, actual_len) = self.parse_a_number()?;
+        assert_eq!(actual_len as usize, len);
 
-        let b = self.consume()?;
-        match b {
-            MinecraftBlock::DarkPrismarine => tuple_struct,
-            _ => Err(MinecraftError::UnexpectedBlock {
-                expected: MinecraftBlock::DarkPrismarine,
-                found: b,
-            }),
-        }
+        self.visit_elements(Container::TupleStruct, visitor)
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::MAP])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::PurpurPillar {
             return Err(MinecraftError::UnexpectedBlock {
@@ -503,7 +674,7 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
             });
         }
 
-        let access = MCMapAccess::new(self, MinecraftBlock::AmethystBlock);
+        let access = MCMapAccess::new(self, Container::Map);
         visitor.visit_map(access)
     }
 
@@ -516,6 +687,8 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[tag::STRUCT])?;
+
         let b = self.consume()?;
         if b != MinecraftBlock::GoldBlock {
             return Err(MinecraftError::UnexpectedBlock {
@@ -527,10 +700,10 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
         let actual_name = self.parse_string()?;
         assert_eq!(name, actual_name);
 
-        let actual_len = self.parse_a_number()? as usize;
-        assert_eq!(actual_len, fields.len());
+        let (_, _, actual_len) = self.parse_a_number()?;
+        assert_eq!(actual_len as usize, fields.len());
 
-        let access = MCMapAccess::new(self, MinecraftBlock::EmeraldBlock);
+        let access = MCMapAccess::new(self, Container::Struct);
         visitor.visit_map(access)
     }
 
@@ -543,6 +716,13 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
     where
         V: serde::de::Visitor<'de>,
     {
+        self.check_tag(&[
+            tag::UNIT_VARIANT,
+            tag::NEWTYPE_VARIANT,
+            tag::TUPLE_VARIANT,
+            tag::STRUCT_VARIANT,
+        ])?;
+
         let marker_block = self.consume()?;
 
         match marker_block {
@@ -568,10 +748,70 @@ impl<'de> serde::de::Deserializer<'de> for &mut MinecraftDeserializer {
             ))),
         }
     }
-    fn deserialize_ignored_any<V>(self, _visitor: V) -> Result<V::Value, Self::Error>
+
+    #[inline(always)]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        todo!()
+        self.deserialize_described(visitor)
     }
 }
+
+#[test]
+fn typed_round_trip_test() {
+    use std::collections::BTreeMap;
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Dot,
+        Circle(f64),
+        Rect(u16, u16),
+        Named { name: String },
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct Build {
+        id: u32,
+        offset: i64,
+        label: Option<String>,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, u8>,
+    }
+
+    let build = Build {
+        id: 12345,
+        offset: -987_654_321,
+        label: Some("spawn".to_string()),
+        shapes: vec![
+            Shape::Dot,
+            Shape::Circle(2.5),
+            Shape::Rect(300, 4),
+            Shape::Named {
+                name: "L".to_string(),
+            },
+        ],
+        tags: BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 200)]),
+    };
+
+    assert_eq!(crate::mock::round_trip(&build).unwrap(), build);
+}
+
+#[test]
+fn option_tag_test() {
+    use crate::{Value, mock};
+
+    for value in [Some(Some(3u8)), Some(None), None] {
+        assert_eq!(mock::round_trip(&value).unwrap(), value);
+    }
+
+    let blocks = mock::serialize(&Some(5u8)).unwrap();
+    assert!(matches!(
+        mock::deserialize::<Option<i32>>(blocks.clone()),
+        Err(MinecraftError::TypeMismatch { .. })
+    ));
+    assert_eq!(
+        mock::deserialize::<Value>(blocks).unwrap(),
+        Value::Option(Some(Box::new(Value::U8(5))))
+    );
+}
@@ -1,7 +1,11 @@
 use serde::de::MapAccess;
 
 use super::MinecraftDeserializer;
-use crate::{MinecraftBlock, result::MinecraftError};
+use crate::{
+    MinecraftBlock,
+    layout::{Container, Shape},
+    result::MinecraftError,
+};
 
 pub(super) struct MCMapAccess<'a> {
     deserializer: &'a mut MinecraftDeserializer,
@@ -10,7 +14,14 @@ pub(super) struct MCMapAccess<'a> {
 }
 
 impl<'a> MCMapAccess<'a> {
-    pub fn new(deserializer: &'a mut MinecraftDeserializer, terminator: MinecraftBlock) -> Self {
+    /// Read the entries of a map-like `container` whose header has been read.
+    pub fn new(deserializer: &'a mut MinecraftDeserializer, container: Container) -> Self {
+        let terminator = match container {
+            Container::Map => MinecraftBlock::AmethystBlock,
+            _ => MinecraftBlock::EmeraldBlock,
+        };
+
+        deserializer.shape(Shape::Open(container));
         Self {
             deserializer,
             terminator,
@@ -30,10 +41,13 @@ impl<'a, 'de> MapAccess<'de> for MCMapAccess<'a> {
             return Ok(None);
         }
 
+        self.deserializer.shape(Shape::Element);
+
         let next_block = self.deserializer.peek()?;
         if next_block == self.terminator {
             // We've reached the end of the map, consume the terminator
             self.deserializer.consume()?;
+            self.deserializer.shape(Shape::Close);
             self.finished = true;
             return Ok(None);
         }
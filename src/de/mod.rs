@@ -1,7 +1,9 @@
 mod main;
 pub use main::MinecraftDeserializer;
 
+mod any;
 mod r#enum;
 
+mod erase;
+
 mod map;
-mod seq;
@@ -0,0 +1,15 @@
+/// How enum variants are identified in the world.
+///
+/// The deserializer must be configured with the same representation the build was written with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EnumRepr {
+    /// Only the variant index. Compact, but reordering variants remaps every stored enum.
+    #[default]
+    Index,
+
+    /// Only the variant name, so variants can be reordered or inserted freely.
+    Name,
+
+    /// The variant name followed by its index.
+    NameAndIndex,
+}
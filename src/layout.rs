@@ -0,0 +1,428 @@
+use std::{fmt::Display, ops::Add};
+
+use serde::{Deserialize, Serialize};
+
+/// A block coordinate in the world.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+impl Position {
+    pub const fn new(x: i32, y: i32, z: i32) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl Add for Position {
+    type Output = Position;
+
+    fn add(self, rhs: Position) -> Self::Output {
+        Position::new(self.x + rhs.x, self.y + rhs.y, self.z + rhs.z)
+    }
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.x, self.y, self.z)
+    }
+}
+
+/// Where the first block of a build goes.
+pub(crate) const ORIGIN: Position = Position::new(-1, 56, 0);
+
+/// A container of the serde data model, as reported to [`Layout::shape`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    Seq,
+    Tuple,
+    TupleStruct,
+    TupleVariant,
+    Map,
+    Struct,
+    StructVariant,
+}
+
+/// A step of the serde walk over a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// A container starts; its header blocks have already been placed.
+    Open(Container),
+
+    /// The next element or map entry of the innermost container starts, or its terminator if
+    /// there are none left. This may be reported more than once for the same index.
+    Element,
+
+    /// The innermost container ends; its terminator has already been placed.
+    Close,
+}
+
+/// Decides where each block of a build goes.
+///
+/// Positions are relative to the origin of the build. Layouts grow northwards (towards negative
+/// z), turn right towards the east (positive x) and stack upwards (positive y).
+pub trait Layout: Send {
+    /// The position of the block at `index` in the block stream.
+    fn position(&self, index: usize) -> Position;
+
+    /// Follow the shape of the value, `index` being the next block to be placed or read.
+    ///
+    /// The serializer and deserializer report the same shapes at the same indices, so a layout
+    /// may move every later block around as long as it never moves the earlier ones.
+    fn shape(&mut self, _index: usize, _shape: Shape) {}
+
+    /// Whether [`Layout::shape`] moves blocks, so that where they go is only known by walking the
+    /// value.
+    fn follows_shape(&self) -> bool {
+        false
+    }
+}
+
+/// Step `distance` blocks in a cardinal direction, counted clockwise from north.
+fn step(direction: usize, distance: i32) -> Position {
+    match direction % 4 {
+        0 => Position::new(0, 0, -distance),
+        1 => Position::new(distance, 0, 0),
+        2 => Position::new(0, 0, distance),
+        _ => Position::new(-distance, 0, 0),
+    }
+}
+
+/// An expanding clockwise spiral, the layout the ComputerCraft scripts always used.
+///
+/// The arms are 1, 2, 2, 3, 3, 4, 4, ... blocks long, starting northwards.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Spiral;
+
+impl Layout for Spiral {
+    fn position(&self, index: usize) -> Position {
+        let mut position = Position::default();
+        let mut remaining = index;
+        let mut arm: usize = 0;
+
+        loop {
+            let length = if arm == 0 { 1 } else { arm.div_ceil(2) + 1 };
+            if remaining <= length {
+                return position + step(arm, remaining as i32);
+            }
+
+            position = position + step(arm, length as i32);
+            remaining -= length;
+            arm += 1;
+        }
+    }
+}
+
+/// A straight line northwards.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Line;
+
+impl Layout for Line {
+    fn position(&self, index: usize) -> Position {
+        step(0, index as i32)
+    }
+}
+
+/// Rows of `width` blocks running east, each row one block north of the previous one.
+#[derive(Debug, Clone, Copy)]
+pub struct Grid {
+    pub width: usize,
+}
+
+impl Layout for Grid {
+    fn position(&self, index: usize) -> Position {
+        let width = self.width.max(1);
+        Position::new((index % width) as i32, 0, -((index / width) as i32))
+    }
+}
+
+/// Like [`Grid`], but every other row runs back west so consecutive blocks always touch.
+#[derive(Debug, Clone, Copy)]
+pub struct Snake {
+    pub width: usize,
+}
+
+impl Layout for Snake {
+    fn position(&self, index: usize) -> Position {
+        let width = self.width.max(1);
+        let (row, column) = (index / width, index % width);
+        let column = match row % 2 {
+            0 => column,
+            _ => width - 1 - column,
+        };
+
+        Position::new(column as i32, 0, -(row as i32))
+    }
+}
+
+/// Layers of `width` by `depth` [`Grid`]s stacked upwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Cube {
+    pub width: usize,
+    pub depth: usize,
+}
+
+impl Layout for Cube {
+    fn position(&self, index: usize) -> Position {
+        let area = self.width.max(1) * self.depth.max(1);
+        let layer = Grid { width: self.width }.position(index % area);
+
+        layer + Position::new(0, (index / area) as i32, 0)
+    }
+}
+
+/// Lays the outermost sequence of a value out as a table.
+///
+/// Each element of the sequence starts a new row one block north of the previous one, and rows
+/// grow eastwards, so a `Vec<Vec<T>>` or a `Vec` of structs reads like a matrix.
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    containers: Vec<Container>,
+
+    /// The index each row after the first starts at.
+    rows: Vec<usize>,
+}
+
+impl Table {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Layout for Table {
+    fn position(&self, index: usize) -> Position {
+        let row = self.rows.partition_point(|&start| start <= index);
+        let start = row.checked_sub(1).map_or(0, |r| self.rows[r]);
+
+        Position::new((index - start) as i32, 0, -(row as i32))
+    }
+
+    fn follows_shape(&self) -> bool {
+        true
+    }
+
+    fn shape(&mut self, index: usize, shape: Shape) {
+        match shape {
+            Shape::Open(container) => self.containers.push(container),
+            Shape::Close => {
+                self.containers.pop();
+            }
+            Shape::Element => {
+                let seqs = self.containers.iter().filter(|&&c| c == Container::Seq);
+                let outermost =
+                    self.containers.last() == Some(&Container::Seq) && seqs.count() == 1;
+
+                if outermost && self.rows.last().is_none_or(|&start| start < index) {
+                    self.rows.push(index);
+                }
+            }
+        }
+    }
+}
+
+/// Lays a value out like an outline, showing how deeply each part of it is nested.
+///
+/// Every element, map entry and terminator starts a new line one block north of the previous one.
+/// Lines grow eastwards and are offset by `indent` once per enclosing container.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    indent: Position,
+    containers: usize,
+
+    /// The index each line starts at, with its depth.
+    lines: Vec<(usize, usize)>,
+}
+
+impl Tree {
+    fn new(indent: Position) -> Self {
+        Self {
+            indent,
+            containers: 0,
+            lines: vec![(0, 0)],
+        }
+    }
+
+    /// Nested lines start one block further east.
+    pub fn indented() -> Self {
+        Self::new(Position::new(1, 0, 0))
+    }
+
+    /// Nested lines start one block higher up.
+    pub fn stacked() -> Self {
+        Self::new(Position::new(0, 1, 0))
+    }
+}
+
+impl Layout for Tree {
+    fn position(&self, index: usize) -> Position {
+        let line = self.lines.partition_point(|&(start, _)| start <= index) - 1;
+        let (start, depth) = self.lines[line];
+        let depth = depth as i32;
+
+        Position::new(
+            self.indent.x * depth + (index - start) as i32,
+            self.indent.y * depth,
+            self.indent.z * depth - line as i32,
+        )
+    }
+
+    fn follows_shape(&self) -> bool {
+        true
+    }
+
+    fn shape(&mut self, index: usize, shape: Shape) {
+        match shape {
+            Shape::Open(_) => self.containers += 1,
+            Shape::Close => self.containers -= 1,
+            Shape::Element => {
+                if self.lines.last().is_some_and(|&(start, _)| start < index) {
+                    self.lines.push((index, self.containers));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn spiral_test() {
+    let positions: Vec<_> = (0..10).map(|i| Spiral.position(i)).collect();
+
+    assert_eq!(
+        positions,
+        [
+            Position::new(0, 0, 0),
+            Position::new(0, 0, -1),
+            Position::new(1, 0, -1),
+            Position::new(2, 0, -1),
+            Position::new(2, 0, 0),
+            Position::new(2, 0, 1),
+            Position::new(1, 0, 1),
+            Position::new(0, 0, 1),
+            Position::new(-1, 0, 1),
+            Position::new(-1, 0, 0),
+        ]
+    );
+}
+
+#[test]
+fn layout_round_trip_test() {
+    use crate::mock;
+    use serde::{Deserialize, Serialize};
+
+    let value = (vec![1u8, 2, 3], "snake".to_string());
+    let placed = mock::place_with(
+        |s| s.with_layout(Snake { width: 4 }),
+        |serializer| value.serialize(serializer),
+    )
+    .unwrap();
+
+    assert_eq!(placed[0].0, ORIGIN);
+    assert_eq!(placed[4].0, ORIGIN + Position::new(3, 0, -1));
+
+    let decoded = mock::read_with(
+        placed,
+        |d| d.with_layout(Snake { width: 4 }),
+        |deserializer| Deserialize::deserialize(deserializer),
+    )
+    .unwrap();
+    assert_eq!(value, decoded);
+}
+
+#[test]
+fn table_test() {
+    use crate::{MinecraftBlock, mock};
+    use serde::{Deserialize, Serialize};
+
+    let matrix: Vec<Vec<u8>> = (0..3)
+        .map(|row| (0..3).map(|c| row * 3 + c).collect())
+        .collect();
+    let placed =
+        mock::place_with(|s| s.with_layout(Table::new()), |s| matrix.serialize(s)).unwrap();
+
+    // Each row is a `CherryLog`, three one-digit numbers and a `DarkPrismarine`.
+    let row = |r: i32| {
+        placed
+            .iter()
+            .filter(|(p, _)| p.z == ORIGIN.z - r)
+            .map(|(_, block)| block.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(row(0), [MinecraftBlock::CherryLog]);
+    for r in 1..=3 {
+        assert_eq!(row(r).len(), 2 + 3 * 3);
+    }
+    assert_eq!(row(4), [MinecraftBlock::DarkPrismarine]);
+
+    let value = mock::read_with(
+        placed.clone(),
+        |d| d.with_layout(Table::new()),
+        |deserializer| crate::Value::deserialize(deserializer),
+    )
+    .unwrap();
+    assert!(matches!(value, crate::Value::Seq(rows) if rows.len() == 3));
+
+    let decoded = mock::read_with(
+        placed,
+        |d| d.with_layout(Table::new()),
+        |deserializer| Vec::<Vec<u8>>::deserialize(deserializer),
+    )
+    .unwrap();
+    assert_eq!(matrix, decoded);
+}
+
+#[test]
+fn tree_test() {
+    use crate::{MinecraftBlock, mock};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Node {
+        Leaf(u8),
+        Branch { children: Vec<Node> },
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Forest {
+        names: BTreeMap<String, (u8, u8)>,
+        root: Node,
+    }
+
+    let forest = Forest {
+        names: BTreeMap::from([("oak".to_string(), (1, 2))]),
+        root: Node::Branch {
+            children: vec![Node::Leaf(3), Node::Branch { children: vec![] }],
+        },
+    };
+
+    for tree in [Tree::indented(), Tree::stacked()] {
+        let placed =
+            mock::place_with(|s| s.with_layout(tree.clone()), |s| forest.serialize(s)).unwrap();
+
+        // The struct closes on its own line, level with its fields.
+        let (last, block) = placed.last().unwrap();
+        assert_eq!(*block, MinecraftBlock::EmeraldBlock);
+        assert_eq!(last.x - ORIGIN.x, tree.indent.x);
+        assert_eq!(last.y - ORIGIN.y, tree.indent.y);
+
+        let value = mock::read_with(
+            placed.clone(),
+            |d| d.with_layout(tree.clone()),
+            |deserializer| crate::Value::deserialize(deserializer),
+        )
+        .unwrap();
+        assert!(
+            matches!(value, crate::Value::Struct(name, fields) if name == "Forest" && fields.len() == 2)
+        );
+
+        let decoded = mock::read_with(
+            placed,
+            |d| d.with_layout(tree),
+            |deserializer| Forest::deserialize(deserializer),
+        )
+        .unwrap();
+        assert_eq!(forest, decoded);
+    }
+}
@@ -1,16 +1,72 @@
+mod bedrock;
+pub use bedrock::{Bedrock, BedrockBlock};
+
 mod blocks;
 pub use blocks::MinecraftBlock;
 
+mod enum_repr;
+pub use enum_repr::EnumRepr;
+
+mod build;
+pub use build::{BuildConfig, Dimension, Facing, Orientation};
+
+mod chunks;
+pub use chunks::{Chunk, ChunkArea, Chunks};
+
 mod de;
 pub use de::MinecraftDeserializer;
 
+mod layout;
+pub use layout::{
+    Container, Cube, Grid, Layout, Line, Position, Shape, Snake, Spiral, Table, Tree,
+};
+
+mod overwrite;
+pub use overwrite::Overwrite;
+
+mod palette;
+pub use palette::Palette;
+
+mod protocol;
+pub use protocol::{
+    Capability, Command, ForceloadAction, Handshake, PROTOCOL_VERSION, Peer, Reply, Request,
+    Response, Role, handshake,
+};
+
+mod rcon;
+pub use rcon::{Rcon, UNKNOWN_BLOCK};
+
+mod regions;
+pub use regions::{Region, Regions};
+
 mod result;
-pub use result::{MinecraftError, MinecraftResult};
+pub use result::{MinecraftError, MinecraftResult, Mismatch};
 
 mod ser;
 pub use ser::MinecraftSerializer;
 
+mod server;
+pub use server::MinecraftServer;
+
+mod shard;
+pub use shard::serialize_sharded;
+
+mod site;
+pub use site::{Computer, Site};
+
+mod transport;
+pub use transport::Transport;
+
+mod transcode;
+pub use transcode::{transcode_from_minecraft, transcode_to_minecraft};
+
+mod value;
+pub use value::{Value, Variant};
+
 mod option_ser;
 
 mod number_marker;
 pub(crate) use number_marker::NumberMarker;
+
+#[cfg(test)]
+mod mock;
@@ -0,0 +1,462 @@
+//! An in-process stand-in for the ComputerCraft scripts, used by the tests.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use tungstenite::{Message, WebSocket, stream::MaybeTlsStream};
+
+use crate::{
+    BuildConfig, Capability, Chunk, Command, Handshake, Layout, MinecraftBlock,
+    MinecraftDeserializer, MinecraftResult, MinecraftSerializer, PROTOCOL_VERSION, Peer, Position,
+    Reply, Request, Response, Role, Spiral,
+};
+
+/// A block that stands in for protected ground, such as spawn, where placements have no effect.
+pub(crate) const PROTECTED: &str = "minecraft:bedrock";
+
+/// A block that makes the next placement over it fail, and then goes away.
+pub(crate) const REFUSED: &str = "minecraft:barrier";
+
+type ComputerSocket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+/// Accept a websocket from a fake computer running `computer` on its own thread.
+pub(crate) fn connect<C, R>(computer: C) -> (WebSocket<TcpStream>, JoinHandle<R>)
+where
+    C: FnOnce(ComputerSocket) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = format!("ws://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (socket, _) = tungstenite::connect(address).unwrap();
+        computer(socket)
+    });
+
+    let (stream, _) = listener.accept().unwrap();
+    (tungstenite::accept(stream).unwrap(), handle)
+}
+
+/// Serialize `value` and return the blocks the computer was asked to place, in order.
+pub(crate) fn serialize<T: Serialize + ?Sized>(value: &T) -> MinecraftResult<Vec<MinecraftBlock>> {
+    serialize_with(|s| s, |serializer| value.serialize(serializer))
+}
+
+/// Run `f` against a serializer set up by `configure` and return the blocks the computer was
+/// asked to place, in order.
+pub(crate) fn serialize_with<C, F>(configure: C, f: F) -> MinecraftResult<Vec<MinecraftBlock>>
+where
+    C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    F: FnOnce(&mut MinecraftSerializer) -> MinecraftResult<()>,
+{
+    let placed = place_with(configure, f)?;
+    Ok(placed.into_iter().map(|(_, block)| block).collect())
+}
+
+/// Run `f` against a serializer set up by `configure` and return every block the computer
+/// placed along with where it went, in order.
+pub(crate) fn place_with<C, F>(
+    configure: C,
+    f: F,
+) -> MinecraftResult<Vec<(Position, MinecraftBlock)>>
+where
+    C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    F: FnOnce(&mut MinecraftSerializer) -> MinecraftResult<()>,
+{
+    let placed = record_with(configure, f)?
+        .into_iter()
+        .filter_map(|command| match command {
+            Command::Place { position, block } => {
+                Some((position, MinecraftBlock::try_from(block.as_str()).unwrap()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    Ok(placed)
+}
+
+/// Run `f` against a serializer set up by `configure` and return every command the computer
+/// received, in order.
+pub(crate) fn record_with<C, F>(configure: C, f: F) -> MinecraftResult<Vec<Command>>
+where
+    C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    F: FnOnce(&mut MinecraftSerializer) -> MinecraftResult<()>,
+{
+    record_in(HashMap::new(), configure, f)
+}
+
+/// Like [`record_with`], but in a world already holding the named blocks of `world` and air
+/// everywhere else. Placements over a [`PROTECTED`] block silently fail, and those over a
+/// [`REFUSED`] block are reported as failed once.
+pub(crate) fn record_in<C, F>(
+    mut world: HashMap<Position, String>,
+    configure: C,
+    f: F,
+) -> MinecraftResult<Vec<Command>>
+where
+    C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    F: FnOnce(&mut MinecraftSerializer) -> MinecraftResult<()>,
+{
+    let (socket, computer) = connect(move |socket| {
+        let mut commands = Vec::new();
+        serve(socket, |command| {
+            commands.push(command.clone());
+            match command {
+                Command::Read { position } => Reply::Block {
+                    name: world
+                        .get(&position)
+                        .cloned()
+                        .unwrap_or_else(|| "minecraft:air".to_string()),
+                },
+                Command::Place { position, block } => {
+                    match world.get(&position).map(String::as_str) {
+                        Some(PROTECTED) => {}
+                        Some(REFUSED) => {
+                            world.remove(&position);
+                            return Reply::Failed {
+                                message: "Could not set the block".to_string(),
+                            };
+                        }
+                        _ => {
+                            world.insert(position, block);
+                        }
+                    }
+                    Reply::Placed { position }
+                }
+                _ => Reply::Done,
+            }
+        });
+        commands
+    });
+
+    let mut serializer = configure(MinecraftSerializer::new(socket));
+    f(&mut serializer)?;
+    drop(serializer);
+
+    Ok(computer.join().unwrap())
+}
+
+/// Run `f` against a serializer set up by `configure` for a computer that keeps count of the
+/// blocks placed for each begun build the way `ser.lua` does, starting from `cursors` and
+/// reporting them in its hello. Returns what `f` returned, the positions placed at and the counts
+/// left once the serializer hangs up.
+pub(crate) fn place_counted<C, F>(
+    cursors: HashMap<String, usize>,
+    configure: C,
+    f: F,
+) -> (MinecraftResult<()>, Vec<Position>, HashMap<String, usize>)
+where
+    C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    F: FnOnce(&mut MinecraftSerializer) -> MinecraftResult<()>,
+{
+    let peer = Peer {
+        role: Role::Ser,
+        version: PROTOCOL_VERSION,
+        capabilities: Capability::ALL.to_vec(),
+        cursors: cursors.clone(),
+        id: None,
+        label: None,
+    };
+
+    let (socket, computer) = connect(move |mut socket| {
+        let (mut cursors, mut build, mut placed) = (cursors, None, Vec::new());
+        while let Ok(Message::Text(text)) = socket.read() {
+            let request: Request = serde_json::from_str(&text).unwrap();
+            let reply = match request.command {
+                Command::Begin { build: key } => {
+                    build = Some(key);
+                    Reply::Done
+                }
+                Command::Complete { build: key } => {
+                    cursors.remove(&key);
+                    build = None;
+                    Reply::Done
+                }
+                Command::Place { position, .. } => {
+                    if let Some(key) = &build {
+                        cursors.insert(key.clone(), request.cursor + 1);
+                    }
+                    placed.push(position);
+                    Reply::Placed { position }
+                }
+                _ => Reply::Done,
+            };
+            let response = Response {
+                seq: request.seq,
+                cursor: request.cursor,
+                reply,
+            };
+            socket
+                .send(Message::text(serde_json::to_string(&response).unwrap()))
+                .unwrap();
+        }
+        (placed, cursors)
+    });
+
+    let mut serializer = configure(MinecraftSerializer::new(socket).with_peer(&peer));
+    let result = f(&mut serializer);
+    drop(serializer);
+
+    let (placed, cursors) = computer.join().unwrap();
+    (result, placed, cursors)
+}
+
+/// Connect to a server at `address` as a computer with `role` whose world holds the named blocks
+/// of `world`, returning the world once the server hangs up.
+pub(crate) fn computer(
+    address: SocketAddr,
+    role: Role,
+    id: u32,
+    world: HashMap<Position, String>,
+) -> JoinHandle<HashMap<Position, String>> {
+    thread::spawn(move || {
+        let (socket, _) = tungstenite::connect(format!("ws://{address}")).unwrap();
+        introduce(socket, role, id, world)
+    })
+}
+
+/// Listen for a connection from this crate, as a relay that a computer with `role` is already
+/// connected to would, returning its URL and a handle to the world once the connection ends.
+pub(crate) fn relay(
+    role: Role,
+    id: u32,
+    world: HashMap<Position, String>,
+) -> (String, JoinHandle<HashMap<Position, String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        introduce(tungstenite::accept(stream).unwrap(), role, id, world)
+    });
+    (url, handle)
+}
+
+/// Say hello on `socket` as a computer with `role` and, if welcomed, place and read blocks in
+/// `world` until the connection ends.
+fn introduce<S: Read + Write>(
+    mut socket: WebSocket<S>,
+    role: Role,
+    id: u32,
+    mut world: HashMap<Position, String>,
+) -> HashMap<Position, String> {
+    let hello = Handshake::Hello {
+        version: PROTOCOL_VERSION,
+        role,
+        capabilities: Capability::ALL.to_vec(),
+        cursors: HashMap::new(),
+        id: Some(id),
+        label: Some(format!("site-{id}")),
+        token: None,
+    };
+    socket
+        .send(Message::text(serde_json::to_string(&hello).unwrap()))
+        .unwrap();
+
+    let answer = socket.read().unwrap();
+    if let Handshake::Welcome { .. } = serde_json::from_str(answer.to_text().unwrap()).unwrap() {
+        serve(socket, |command| match command {
+            Command::Read { position } => Reply::Block {
+                name: world
+                    .get(&position)
+                    .cloned()
+                    .unwrap_or_else(|| "minecraft:air".to_string()),
+            },
+            Command::Place { position, block } => {
+                if world.get(&position).map(String::as_str) != Some(PROTECTED) {
+                    world.insert(position, block);
+                }
+                Reply::Placed { position }
+            }
+            _ => Reply::Done,
+        });
+    }
+    world
+}
+
+/// Answer every request on `socket` with `answer` until it is closed.
+fn serve<S: Read + Write>(mut socket: WebSocket<S>, mut answer: impl FnMut(Command) -> Reply) {
+    while let Ok(Message::Text(text)) = socket.read() {
+        let request: Request = serde_json::from_str(&text).unwrap();
+        let response = Response {
+            seq: request.seq,
+            cursor: request.cursor,
+            reply: answer(request.command),
+        };
+        socket
+            .send(Message::text(serde_json::to_string(&response).unwrap()))
+            .unwrap();
+    }
+}
+
+/// Deserialize a `T` from `blocks`, checking that every block was consumed.
+pub(crate) fn deserialize<T: DeserializeOwned>(blocks: Vec<MinecraftBlock>) -> MinecraftResult<T> {
+    deserialize_with(blocks, |d| d, |deserializer| T::deserialize(deserializer))
+}
+
+/// Run `f` against a deserializer set up by `configure` and reading `blocks` laid out along the
+/// default layout, checking that every block was consumed.
+pub(crate) fn deserialize_with<C, F, R>(
+    blocks: Vec<MinecraftBlock>,
+    configure: C,
+    f: F,
+) -> MinecraftResult<R>
+where
+    C: FnOnce(MinecraftDeserializer) -> MinecraftDeserializer,
+    F: FnOnce(&mut MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let placed = blocks
+        .into_iter()
+        .enumerate()
+        .map(|(index, block)| (BuildConfig::default().place(Spiral.position(index)), block))
+        .collect();
+
+    read_with(placed, configure, f)
+}
+
+/// Run `f` against a deserializer set up by `configure` in a world holding `placed`, checking
+/// that every placed block was read.
+pub(crate) fn read_with<C, F, R>(
+    placed: Vec<(Position, MinecraftBlock)>,
+    configure: C,
+    f: F,
+) -> MinecraftResult<R>
+where
+    C: FnOnce(MinecraftDeserializer) -> MinecraftDeserializer,
+    F: FnOnce(&mut MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let len = placed.len();
+    let (value, read, _) = run_world(placed, |d| f(&mut configure(d)))?;
+
+    assert_eq!(read, len, "not every block was consumed");
+    Ok(value)
+}
+
+/// Run `f` against a deserializer set up by `configure` in a world holding `placed`, and return
+/// what is left of the world afterwards.
+pub(crate) fn read_in<C, F, R>(
+    placed: Vec<(Position, MinecraftBlock)>,
+    configure: C,
+    f: F,
+) -> MinecraftResult<(R, HashMap<Position, MinecraftBlock>)>
+where
+    C: FnOnce(MinecraftDeserializer) -> MinecraftDeserializer,
+    F: FnOnce(&mut MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let (value, _, world) = run_world(placed, |d| f(&mut configure(d)))?;
+    Ok((value, world))
+}
+
+/// Hand a deserializer over a world holding `placed` to `f`, and return what is left of the
+/// world afterwards.
+pub(crate) fn give_world<F, R>(
+    placed: Vec<(Position, MinecraftBlock)>,
+    f: F,
+) -> MinecraftResult<(R, HashMap<Position, MinecraftBlock>)>
+where
+    F: FnOnce(MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let (value, _, world) = run_world(placed, f)?;
+    Ok((value, world))
+}
+
+/// Run `f` against a deserializer set up by `configure` in a world holding the named blocks of
+/// `world` and air everywhere else.
+pub(crate) fn read_named<C, F, R>(
+    world: HashMap<Position, String>,
+    configure: C,
+    f: F,
+) -> MinecraftResult<R>
+where
+    C: FnOnce(MinecraftDeserializer) -> MinecraftDeserializer,
+    F: FnOnce(&mut MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let (socket, computer) = connect(move |socket| {
+        serve(socket, |command| match command {
+            Command::Read { position } => Reply::Block {
+                name: world
+                    .get(&position)
+                    .cloned()
+                    .unwrap_or_else(|| "minecraft:air".to_string()),
+            },
+            _ => Reply::Done,
+        })
+    });
+
+    let value = f(&mut configure(MinecraftDeserializer::new(socket)))?;
+    computer.join().unwrap();
+    Ok(value)
+}
+
+/// Serve `placed` to a deserializer, returning the result of `f`, how many of the placed blocks
+/// were read and the world afterwards.
+fn run_world<F, R>(
+    placed: Vec<(Position, MinecraftBlock)>,
+    f: F,
+) -> MinecraftResult<(R, usize, HashMap<Position, MinecraftBlock>)>
+where
+    F: FnOnce(MinecraftDeserializer) -> MinecraftResult<R>,
+{
+    let mut world: HashMap<Position, MinecraftBlock> = placed.into_iter().collect();
+
+    let (socket, computer) = connect(move |socket| {
+        // Only the chunks something was built in are loaded.
+        let loaded: HashSet<Chunk> = world.keys().map(|&p| Chunk::containing(p)).collect();
+
+        let mut read = HashSet::new();
+        serve(socket, |command| match command {
+            Command::Read { position } => {
+                let reply = match world.get(&position) {
+                    Some(block) => Reply::Block {
+                        name: block.to_string(),
+                    },
+                    None if !loaded.contains(&Chunk::containing(position)) => Reply::Unloaded,
+                    None => Reply::Block {
+                        name: "minecraft:air".to_string(),
+                    },
+                };
+
+                if world.contains_key(&position) {
+                    read.insert(position);
+                }
+                reply
+            }
+            Command::Place { position, block } => {
+                fill(&mut world, position, position, &block);
+                Reply::Placed { position }
+            }
+            Command::Fill { from, to, block } => {
+                fill(&mut world, from, to, &block);
+                Reply::Done
+            }
+            _ => Reply::Done,
+        });
+        (read.len(), world)
+    });
+
+    let value = f(MinecraftDeserializer::new(socket))?;
+
+    let (read, world) = computer.join().unwrap();
+    Ok((value, read, world))
+}
+
+/// Set every block in the box between `from` and `to` to `block`, which only erasing does.
+fn fill(world: &mut HashMap<Position, MinecraftBlock>, from: Position, to: Position, block: &str) {
+    assert_eq!(block, "minecraft:air");
+
+    let within = |a: i32, b: i32, c: i32| a.min(b) <= c && c <= a.max(b);
+    world.retain(|p, _| {
+        !(within(from.x, to.x, p.x) && within(from.y, to.y, p.y) && within(from.z, to.z, p.z))
+    });
+}
+
+/// Serialize `value` and deserialize it back through the fake computer.
+pub(crate) fn round_trip<T: Serialize + DeserializeOwned>(value: &T) -> MinecraftResult<T> {
+    deserialize(serialize(value)?)
+}
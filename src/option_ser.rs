@@ -1,27 +1,59 @@
 use serde::Serialize;
 
-use crate::{MinecraftBlock, MinecraftError, MinecraftSerializer};
+use crate::{MinecraftBlock, MinecraftError, MinecraftResult, MinecraftSerializer};
+
+macro_rules! tags {
+    ($($name:ident = $index:literal),*) => {
+        $(pub(crate) const $name: u8 = $index;)*
+
+        /// Human readable names of the tags, indexed by tag.
+        pub(crate) const NAMES: [&str; 28] = [$(stringify!($name)),*];
+    };
+}
+
+/// Type tags written after the `CoalBlock` of a `Some`, one per serde data model method.
+pub(crate) mod tag {
+    use super::*;
+
+    tags! {
+        BOOL = 0, I8 = 1, I16 = 2, I32 = 3, I64 = 4, U8 = 5, U16 = 6, U32 = 7, U64 = 8,
+        F32 = 9, F64 = 10, CHAR = 11, STR = 12, BYTES = 13, NONE = 14, UNIT = 15,
+        UNIT_STRUCT = 16, UNIT_VARIANT = 17, NEWTYPE_STRUCT = 18, NEWTYPE_VARIANT = 19,
+        SEQ = 20, TUPLE = 21, TUPLE_STRUCT = 22, TUPLE_VARIANT = 23, MAP = 24, STRUCT = 25,
+        STRUCT_VARIANT = 26, SOME = 27
+    }
+
+    /// Read a tag back from its block.
+    pub(crate) fn from_block(block: MinecraftBlock) -> MinecraftResult<u8> {
+        match block.clone().block_to_bit()? {
+            tag if (tag as usize) < NAMES.len() => Ok(tag),
+            _ => Err(MinecraftError::InvalidBlockSequence(format!(
+                "option tag {block}"
+            ))),
+        }
+    }
+}
 
 macro_rules! serialize {
-    ($index:literal = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*) => $R:ident) => {
+    ($tag:ident = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*) => $R:ident) => {
         fn $method$(<$T: ?Sized + Serialize>)?(self, $($v: $arg),*) -> Result<Self::$R, Self::Error> {
             self.serializer
-                .place_block(MinecraftBlock::bit_to_block($index)?)?;
+                .place_block(MinecraftBlock::bit_to_block(tag::$tag)?)?;
             self.serializer.$method($($v),*)
         }
     };
 
-    ($index:literal = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*)) => {
+    ($tag:ident = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*)) => {
         fn $method$(<$T: ?Sized + Serialize>)?(self, $($v: $arg),*) -> Result<Self::Ok, Self::Error> {
             self.serializer
-                .place_block(MinecraftBlock::bit_to_block($index)?)?;
+                .place_block(MinecraftBlock::bit_to_block(tag::$tag)?)?;
             self.serializer.$method($($v),*)
         }
     };
 
-    {$($index:literal = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*) $(=> $R:ident)?),*} => {
+    {$($tag:ident = $method:ident$(<$T:tt>)?($($v:ident: $arg:ty),*) $(=> $R:ident)?),*} => {
         $(
-            serialize!($index = $method$(<$T>)?($($v: $arg),*) $(=> $R)?);
+            serialize!($tag = $method$(<$T>)?($($v: $arg),*) $(=> $R)?);
         )*
     };
 }
@@ -49,39 +81,33 @@ impl<'a> serde::ser::Serializer for OptionSerializer<'a> {
     type SerializeStructVariant = &'a mut MinecraftSerializer;
 
     serialize! {
-        0 = serialize_bool(v: bool),
-        1 = serialize_i8(v: i8),
-        2 = serialize_i16(v: i16),
-        3 = serialize_i32(v: i32),
-        4 = serialize_i64(v: i64),
-        5 = serialize_u8(v: u8),
-        6 = serialize_u16(v: u16),
-        7 = serialize_u32(v: u32),
-        8 = serialize_u64(v: u64),
-        9 = serialize_f32(v: f32),
-        10 = serialize_f64(v: f64),
-        11 = serialize_char(v: char),
-        12 = serialize_str(v: &str),
-        13 = serialize_bytes(v: &[u8]),
-        14 = serialize_none(),
-        15 = serialize_unit(),
-        16 = serialize_unit_struct(v: &'static str),
-        17 = serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str),
-        18 = serialize_newtype_struct<T>(name: &'static str,value: &T),
-        19 = serialize_newtype_variant<T>(name: &'static str, variant_index: u32, variant: &'static str, value: &T),
-        20 = serialize_seq(len: Option<usize>) => SerializeSeq,
-        21 = serialize_tuple(len: usize) => SerializeTuple,
-        22 = serialize_tuple_struct(name: &'static str, len: usize) => SerializeTupleStruct,
-        23 = serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) => SerializeTupleVariant,
-        24 = serialize_map(len: Option<usize>) => SerializeMap,
-        25 = serialize_struct(name: &'static str, len: usize) => SerializeStruct,
-        26 = serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) => SerializeStructVariant
-    }
-
-    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
-    where
-        T: ?Sized + Serialize,
-    {
-        self.serializer.serialize_some(value)
+        BOOL = serialize_bool(v: bool),
+        I8 = serialize_i8(v: i8),
+        I16 = serialize_i16(v: i16),
+        I32 = serialize_i32(v: i32),
+        I64 = serialize_i64(v: i64),
+        U8 = serialize_u8(v: u8),
+        U16 = serialize_u16(v: u16),
+        U32 = serialize_u32(v: u32),
+        U64 = serialize_u64(v: u64),
+        F32 = serialize_f32(v: f32),
+        F64 = serialize_f64(v: f64),
+        CHAR = serialize_char(v: char),
+        STR = serialize_str(v: &str),
+        BYTES = serialize_bytes(v: &[u8]),
+        NONE = serialize_none(),
+        UNIT = serialize_unit(),
+        UNIT_STRUCT = serialize_unit_struct(v: &'static str),
+        UNIT_VARIANT = serialize_unit_variant(name: &'static str, variant_index: u32, variant: &'static str),
+        NEWTYPE_STRUCT = serialize_newtype_struct<T>(name: &'static str,value: &T),
+        NEWTYPE_VARIANT = serialize_newtype_variant<T>(name: &'static str, variant_index: u32, variant: &'static str, value: &T),
+        SEQ = serialize_seq(len: Option<usize>) => SerializeSeq,
+        TUPLE = serialize_tuple(len: usize) => SerializeTuple,
+        TUPLE_STRUCT = serialize_tuple_struct(name: &'static str, len: usize) => SerializeTupleStruct,
+        TUPLE_VARIANT = serialize_tuple_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) => SerializeTupleVariant,
+        MAP = serialize_map(len: Option<usize>) => SerializeMap,
+        STRUCT = serialize_struct(name: &'static str, len: usize) => SerializeStruct,
+        STRUCT_VARIANT = serialize_struct_variant(name: &'static str, variant_index: u32, variant: &'static str, len: usize) => SerializeStructVariant,
+        SOME = serialize_some<T>(value: &T)
     }
 }
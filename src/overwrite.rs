@@ -0,0 +1,90 @@
+use crate::{Dimension, MinecraftBlock, Position, Region};
+
+/// Which blocks the serializer may replace when placing a build.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Overwrite {
+    /// Place blocks without looking at what is there.
+    #[default]
+    Anything,
+
+    /// Only replace air and the blocks of earlier builds, that is blocks serdecraft builds are
+    /// made of lying within one of these regions, usually [`crate::Regions::regions`].
+    Builds(Vec<Region>),
+
+    /// Only replace air.
+    Air,
+}
+
+impl Overwrite {
+    /// Whether the block named `name` at `position` in `dimension` may be replaced.
+    pub fn allows(&self, dimension: Dimension, position: Position, name: &str) -> bool {
+        let air = matches!(
+            name,
+            "minecraft:air" | "minecraft:cave_air" | "minecraft:void_air"
+        );
+        match self {
+            Overwrite::Anything => true,
+            Overwrite::Builds(regions) => {
+                air || MinecraftBlock::try_from(name).is_ok()
+                    && regions
+                        .iter()
+                        .any(|r| r.build.dimension == dimension && r.contains(position))
+            }
+            Overwrite::Air => air,
+        }
+    }
+}
+
+#[test]
+fn overwrite_test() {
+    use crate::{MinecraftError, Position, layout::ORIGIN, mock};
+    use serde::Serialize;
+    use std::collections::HashMap;
+
+    let value = vec![1u8, 2];
+    let build = |overwrite: Overwrite| {
+        move |s: crate::MinecraftSerializer| s.with_layout(crate::Line).with_overwrite(overwrite)
+    };
+    let place = |s: &mut crate::MinecraftSerializer| {
+        value.serialize(&mut *s)?;
+        s.finish()
+    };
+
+    // An earlier build recorded right where the new one goes.
+    let spot = ORIGIN + Position::new(0, 0, -1);
+    let earlier = Region {
+        name: "earlier".to_string(),
+        build: crate::BuildConfig::new().with_origin(spot),
+        min: spot,
+        max: spot + Position::new(3, 0, 0),
+        len: 4,
+    };
+    let builds = || Overwrite::Builds(vec![earlier.clone()]);
+
+    // A chest, and natural stone where no build was ever recorded, are kept.
+    let chest = HashMap::from([(spot, "minecraft:chest".to_string())]);
+    let terrain = HashMap::from([(spot, MinecraftBlock::Stone.to_string())]);
+    for (world, overwrite) in [
+        (chest.clone(), builds()),
+        (chest, Overwrite::Air),
+        (terrain.clone(), Overwrite::Builds(Vec::new())),
+        (terrain.clone(), Overwrite::Air),
+    ] {
+        let found = world[&spot].clone();
+        assert!(matches!(
+            mock::record_in(world, build(overwrite), place),
+            Err(MinecraftError::Occupied(blocks)) if blocks == [(spot, found)]
+        ));
+    }
+
+    // The same stone inside the recorded region belongs to the earlier build.
+    let commands = mock::record_in(terrain, build(builds()), place).unwrap();
+    assert!(
+        commands
+            .iter()
+            .any(|c| matches!(c, crate::Command::Place { position, .. } if *position == spot))
+    );
+
+    let blocks = mock::serialize_with(build(Overwrite::Air), place).unwrap();
+    assert_eq!(blocks, mock::serialize(&value).unwrap());
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::{MinecraftBlock, MinecraftError, MinecraftResult};
+
+/// The blocks that stand in the world for the blocks of the encoding, so that a site can build
+/// with materials of its own. Every block stands for itself by default.
+///
+/// The serializer and the deserializer of a build must use the same palette.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Palette {
+    names: HashMap<MinecraftBlock, String>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Place the block named `name` wherever `block` belongs, failing if another block already
+    /// stands for it.
+    pub fn with(mut self, block: MinecraftBlock, name: impl Into<String>) -> MinecraftResult<Self> {
+        let name = name.into();
+        if let Some(other) = MinecraftBlock::ALL
+            .iter()
+            .find(|&other| *other != block && self.name(other) == name)
+        {
+            return Err(MinecraftError::PaletteClash {
+                name,
+                block: other.clone(),
+            });
+        }
+
+        self.names.insert(block, name);
+        Ok(self)
+    }
+
+    /// The name of the block placed for `block`.
+    pub fn name(&self, block: &MinecraftBlock) -> String {
+        match self.names.get(block) {
+            Some(name) => name.clone(),
+            None => block.to_string(),
+        }
+    }
+
+    /// The block that the block named `name` stands for.
+    pub fn block(&self, name: &str) -> MinecraftResult<MinecraftBlock> {
+        if let Some((block, _)) = self.names.iter().find(|(_, n)| *n == name) {
+            return Ok(block.clone());
+        }
+
+        match MinecraftBlock::try_from(name)? {
+            block if self.names.contains_key(&block) => {
+                Err(MinecraftError::UnknownBlockType(name.to_string()))
+            }
+            block => Ok(block),
+        }
+    }
+}
+
+#[test]
+fn palette_test() {
+    use crate::{Command, Line, mock};
+    use serde::{Deserialize, Serialize};
+
+    let palette = Palette::new()
+        .with(MinecraftBlock::RedWool, "minecraft:red_concrete_powder")
+        .unwrap();
+    assert_eq!(
+        palette.name(&MinecraftBlock::RedWool),
+        "minecraft:red_concrete_powder"
+    );
+    assert_eq!(
+        palette.block("minecraft:red_concrete_powder").unwrap(),
+        MinecraftBlock::RedWool
+    );
+    assert!(palette.block(&MinecraftBlock::RedWool.to_string()).is_err());
+    assert!(matches!(
+        palette
+            .clone()
+            .with(MinecraftBlock::BlueWool, "minecraft:red_concrete_powder"),
+        Err(MinecraftError::PaletteClash {
+            block: MinecraftBlock::RedWool,
+            ..
+        })
+    ));
+    assert!(
+        palette
+            .clone()
+            .with(
+                MinecraftBlock::BlueWool,
+                MinecraftBlock::RedWool.to_string()
+            )
+            .is_ok()
+    );
+
+    let value = vec![Some("palette".to_string()), None];
+    let palette = MinecraftBlock::ALL
+        .iter()
+        .fold(Palette::new(), |palette, block| {
+            let name = format!("{block}_painted");
+            palette.with(block.clone(), name).unwrap()
+        });
+
+    let world: HashMap<_, _> = mock::record_with(
+        |s| s.with_layout(Line).with_palette(palette.clone()),
+        |s| value.serialize(s),
+    )
+    .unwrap()
+    .into_iter()
+    .filter_map(|command| match command {
+        Command::Place { position, block } => Some((position, block)),
+        _ => None,
+    })
+    .collect();
+    assert!(world.values().all(|name| name.ends_with("_painted")));
+
+    let read = mock::read_named(
+        world.clone(),
+        |d| d.with_layout(Line).with_palette(palette),
+        |d| <Vec<Option<String>>>::deserialize(d),
+    );
+    assert_eq!(read.unwrap(), value);
+
+    // A deserializer with another palette does not recognise the blocks.
+    let read = mock::read_named(
+        world,
+        |d| d.with_layout(Line),
+        |d| <Vec<Option<String>>>::deserialize(d),
+    );
+    assert!(matches!(read, Err(MinecraftError::UnknownBlockType(_))));
+}
@@ -0,0 +1,580 @@
+//! The messages exchanged with the ComputerCraft scripts, sent as JSON text frames.
+
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Deserializer, Serialize, de::value::StringDeserializer};
+
+use crate::{BuildConfig, Chunks, Dimension, MinecraftError, MinecraftResult, Position, Transport};
+
+/// The version of the protocol spoken by this crate, sent along with every request.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// What a computer connects for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// Placing the blocks of a serializer.
+    Ser,
+
+    /// Reading the blocks of a deserializer.
+    De,
+}
+
+/// A command the ComputerCraft scripts may or may not understand, besides [`Command::Place`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    Read,
+    Fill,
+    Forceload,
+    Dimension,
+
+    /// Keeping count of the blocks placed for a build, see [`Command::Begin`].
+    Resume,
+}
+
+impl Capability {
+    /// Every capability this crate can make use of.
+    pub const ALL: [Capability; 5] = [
+        Capability::Read,
+        Capability::Fill,
+        Capability::Forceload,
+        Capability::Dimension,
+        Capability::Resume,
+    ];
+
+    /// The capabilities a build needs from the computer.
+    pub(crate) fn required_by(build: BuildConfig) -> Vec<Capability> {
+        let mut required = Vec::new();
+        if build.dimension != Dimension::Overworld {
+            required.push(Capability::Dimension);
+        }
+        if build.chunks == Chunks::Forceload {
+            required.push(Capability::Forceload);
+        }
+        required
+    }
+}
+
+/// The frames that open a session, before any [`Request`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Handshake {
+    /// Sent by the computer as soon as it connects.
+    Hello {
+        version: u32,
+        role: Role,
+
+        /// Capabilities this crate does not know are dropped.
+        #[serde(deserialize_with = "known_capabilities")]
+        capabilities: Vec<Capability>,
+
+        /// How many blocks of each unfinished build the computer has placed, by
+        /// [`BuildConfig::key`].
+        #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+        cursors: HashMap<String, usize>,
+
+        /// The computer's id and label, which tell build sites apart.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+
+        /// The token shared with the Rust application, if it asks for one.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+
+    /// The answer to a computer this crate can work with, listing the capabilities both know.
+    Welcome {
+        version: u32,
+        capabilities: Vec<Capability>,
+    },
+
+    /// The answer to a computer this crate cannot work with.
+    Refused { message: String },
+}
+
+/// Read a list of capabilities, dropping the ones this crate does not know, such as those of a
+/// newer computer.
+fn known_capabilities<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Capability>, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    Ok(names
+        .into_iter()
+        .filter_map(|name| {
+            let name = StringDeserializer::<serde::de::value::Error>::new(name);
+            Capability::deserialize(name).ok()
+        })
+        .collect())
+}
+
+/// A computer that has completed the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub role: Role,
+    pub version: u32,
+    pub capabilities: Vec<Capability>,
+
+    /// How many blocks of each unfinished build the computer has placed, by
+    /// [`BuildConfig::key`].
+    pub cursors: HashMap<String, usize>,
+
+    pub id: Option<u32>,
+    pub label: Option<String>,
+}
+
+impl Peer {
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// How many blocks of `build` the computer placed before it was interrupted, to resume it
+    /// with [`crate::MinecraftSerializer::with_resume`]; `None` if `build` is not unfinished.
+    pub fn cursor(&self, build: BuildConfig) -> Option<usize> {
+        self.cursors.get(&build.key()).copied()
+    }
+}
+
+impl Display for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.id, &self.label) {
+            (Some(id), Some(label)) => write!(f, "computer {id} ({label})"),
+            (Some(id), None) => write!(f, "computer {id}"),
+            (None, Some(label)) => write!(f, "computer {label}"),
+            (None, None) => write!(f, "unnamed computer"),
+        }
+    }
+}
+
+/// Wait for the computer on `transport` to introduce itself, and welcome or refuse it.
+///
+/// Computers that do not present `token` when one is given, computers speaking another protocol
+/// version, deserializing computers that cannot read blocks and frames that are not a hello at
+/// all are refused. Capabilities this crate does not know are ignored. Pass the [`Peer`] to
+/// [`crate::MinecraftSerializer::with_peer`] or [`crate::MinecraftDeserializer::with_peer`] so
+/// that they only use what the computer supports.
+pub fn handshake(transport: &mut impl Transport, token: Option<&str>) -> MinecraftResult<Peer> {
+    let hello = match serde_json::from_str::<Handshake>(&transport.receive_frame()?) {
+        Ok(hello) => hello,
+        Err(e) => {
+            let message = format!("malformed hello: {e}");
+            let refused = Handshake::Refused {
+                message: message.clone(),
+            };
+            transport.send_frame(serde_json::to_string(&refused)?)?;
+            return Err(MinecraftError::Handshake(message));
+        }
+    };
+    let Handshake::Hello {
+        version,
+        role,
+        capabilities,
+        cursors,
+        id,
+        label,
+        token: presented,
+    } = hello
+    else {
+        return Err(MinecraftError::Handshake(format!(
+            "expected hello, got {hello:?}"
+        )));
+    };
+
+    let peer = Peer {
+        role,
+        version,
+        capabilities: capabilities.clone(),
+        cursors,
+        id,
+        label,
+    };
+
+    let authorized = match (token, &presented) {
+        (Some(token), Some(presented)) => same_secret(token, presented),
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+
+    let refusal = if !authorized {
+        Some(MinecraftError::Unauthorized)
+    } else if version != PROTOCOL_VERSION {
+        Some(MinecraftError::Incompatible {
+            ours: PROTOCOL_VERSION,
+            theirs: version,
+        })
+    } else if role == Role::De && !peer.supports(Capability::Read) {
+        Some(MinecraftError::Unsupported(Capability::Read))
+    } else {
+        None
+    };
+
+    let answer = match &refusal {
+        Some(error) => Handshake::Refused {
+            message: error.to_string(),
+        },
+        None => Handshake::Welcome {
+            version: PROTOCOL_VERSION,
+            capabilities,
+        },
+    };
+    transport.send_frame(serde_json::to_string(&answer)?)?;
+
+    match refusal {
+        Some(error) => Err(error),
+        None => Ok(peer),
+    }
+}
+
+/// Compare two secrets in time that does not depend on where they differ.
+fn same_secret(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Something the computer is asked to do.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Command {
+    /// Run every later command in the dimension with this identifier. Answered with
+    /// [`Reply::Done`].
+    Dimension { id: String },
+
+    /// Add or remove the forceload of the chunk holding the block column at `x`, `z`. Answered
+    /// with [`Reply::Done`].
+    Forceload {
+        action: ForceloadAction,
+        x: i32,
+        z: i32,
+    },
+
+    /// Look at a block. Answered with [`Reply::Block`] or [`Reply::Unloaded`].
+    Read { position: Position },
+
+    /// Set a block. Answered with [`Reply::Placed`] or [`Reply::Failed`].
+    Place { position: Position, block: String },
+
+    /// Keep count of the blocks placed from now on for the build with this [`BuildConfig::key`],
+    /// and report it in later hellos until the build is complete. Answered with [`Reply::Done`].
+    Begin { build: String },
+
+    /// The build with this key is complete, so its count can be forgotten. Answered with
+    /// [`Reply::Done`].
+    Complete { build: String },
+
+    /// Set every block in the box between two corners. Answered with [`Reply::Done`].
+    Fill {
+        from: Position,
+        to: Position,
+        block: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceloadAction {
+    Add,
+    Remove,
+}
+
+/// A numbered [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub version: u32,
+
+    /// Counts the requests of a session, starting at 1.
+    pub seq: u64,
+
+    /// Index of the block in the block stream that the session has reached.
+    pub cursor: usize,
+
+    #[serde(flatten)]
+    pub command: Command,
+}
+
+/// What the computer has to say about a [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Reply {
+    Done,
+    Block {
+        name: String,
+    },
+    Unloaded,
+
+    /// The game ran the `setblock` at `position`.
+    Placed {
+        position: Position,
+    },
+
+    /// The game refused the `setblock`, with its error text.
+    Failed {
+        message: String,
+    },
+
+    /// The computer could not make sense of the request.
+    Error {
+        message: String,
+    },
+}
+
+/// A [`Reply`] along with the `seq` and `cursor` of the request it answers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Response {
+    pub seq: u64,
+    pub cursor: usize,
+
+    #[serde(flatten)]
+    pub reply: Reply,
+}
+
+/// A connection to a computer, numbering the requests sent over it.
+pub(crate) struct Channel {
+    transport: Box<dyn Transport>,
+    seq: u64,
+
+    /// The computer, if it went through the handshake; otherwise it is assumed to support
+    /// everything.
+    peer: Option<Peer>,
+}
+
+impl Channel {
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self {
+            transport: Box::new(transport),
+            seq: 0,
+            peer: None,
+        }
+    }
+
+    pub fn set_peer(&mut self, peer: &Peer) {
+        self.peer = Some(peer.clone());
+    }
+
+    pub fn peer(&self) -> Option<&Peer> {
+        self.peer.as_ref()
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.peer.as_ref().is_none_or(|p| p.supports(capability))
+    }
+
+    /// Fail unless the computer supports everything in `required`.
+    pub fn require(&self, required: &[Capability]) -> MinecraftResult<()> {
+        match required.iter().find(|&&c| !self.supports(c)) {
+            Some(&missing) => Err(MinecraftError::Unsupported(missing)),
+            None => Ok(()),
+        }
+    }
+
+    /// Send `command` and wait for the reply to it, failing if the reply answers another request.
+    pub fn call(&mut self, cursor: usize, command: Command) -> MinecraftResult<Reply> {
+        self.seq += 1;
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            seq: self.seq,
+            cursor,
+            command,
+        };
+        self.transport
+            .send_frame(serde_json::to_string(&request)?)?;
+
+        let response: Response = serde_json::from_str(&self.transport.receive_frame()?)?;
+        if (response.seq, response.cursor) != (request.seq, cursor) {
+            return Err(MinecraftError::Desync {
+                seq: request.seq,
+                cursor,
+                found_seq: response.seq,
+                found_cursor: response.cursor,
+            });
+        }
+
+        match response.reply {
+            Reply::Error { message } => Err(MinecraftError::Computer(message)),
+            reply => Ok(reply),
+        }
+    }
+}
+
+#[test]
+fn desync_test() {
+    use crate::mock;
+    use tungstenite::Message;
+
+    let frame = serde_json::to_value(Request {
+        version: PROTOCOL_VERSION,
+        seq: 1,
+        cursor: 0,
+        command: Command::Read {
+            position: Position::new(1, 2, 3),
+        },
+    })
+    .unwrap();
+    assert_eq!(
+        frame,
+        serde_json::json!({
+            "version": PROTOCOL_VERSION,
+            "seq": 1,
+            "cursor": 0,
+            "type": "read",
+            "position": { "x": 1, "y": 2, "z": 3 },
+        })
+    );
+
+    // A computer that answers a request it was never sent.
+    let (socket, computer) = mock::connect(|mut socket| {
+        let request: Request =
+            serde_json::from_str(socket.read().unwrap().to_text().unwrap()).unwrap();
+        let stale = Response {
+            seq: request.seq + 1,
+            cursor: request.cursor,
+            reply: Reply::Done,
+        };
+        socket
+            .send(Message::text(serde_json::to_string(&stale).unwrap()))
+            .unwrap();
+    });
+
+    let mut channel = Channel::new(socket);
+    let id = "minecraft:overworld".to_string();
+    assert!(matches!(
+        channel.call(7, Command::Dimension { id }),
+        Err(MinecraftError::Desync {
+            seq: 1,
+            cursor: 7,
+            found_seq: 2,
+            found_cursor: 7
+        })
+    ));
+    computer.join().unwrap();
+}
+
+#[test]
+fn handshake_test() {
+    use crate::{MinecraftSerializer, mock};
+    use serde::Serialize;
+    use tungstenite::Message;
+
+    // A computer that introduces itself and returns the answer it got.
+    let hello = |version, role, capabilities: &[Capability], token: Option<&str>| {
+        let hello = Handshake::Hello {
+            version,
+            role,
+            capabilities: capabilities.to_vec(),
+            cursors: HashMap::from([(BuildConfig::default().key(), 3)]),
+            id: None,
+            label: None,
+            token: token.map(str::to_string),
+        };
+        mock::connect(move |mut socket| {
+            socket
+                .send(Message::text(serde_json::to_string(&hello).unwrap()))
+                .unwrap();
+            let answer = socket.read().unwrap();
+            while socket.read().is_ok() {}
+            serde_json::from_str::<Handshake>(answer.to_text().unwrap()).unwrap()
+        })
+    };
+
+    // A newer computer with a capability this crate does not know, and one saying nonsense.
+    let raw = |frame: serde_json::Value| {
+        mock::connect(move |mut socket| {
+            socket.send(Message::text(frame.to_string())).unwrap();
+            let answer = socket.read().unwrap();
+            while socket.read().is_ok() {}
+            serde_json::from_str::<Handshake>(answer.to_text().unwrap()).unwrap()
+        })
+    };
+    let (mut socket, computer) = raw(serde_json::json!({
+        "type": "hello",
+        "version": PROTOCOL_VERSION,
+        "role": "ser",
+        "capabilities": ["teleport", "fill"],
+    }));
+    let peer = handshake(&mut socket, None).unwrap();
+    assert_eq!(peer.capabilities, [Capability::Fill]);
+    drop(socket);
+    assert!(matches!(
+        computer.join().unwrap(),
+        Handshake::Welcome { capabilities, .. } if capabilities == [Capability::Fill]
+    ));
+
+    let (mut socket, computer) =
+        raw(serde_json::json!({ "type": "hello", "version": PROTOCOL_VERSION }));
+    assert!(matches!(
+        handshake(&mut socket, None),
+        Err(MinecraftError::Handshake(_))
+    ));
+    drop(socket);
+    assert!(matches!(
+        computer.join().unwrap(),
+        Handshake::Refused { .. }
+    ));
+
+    for token in [None, Some("guess")] {
+        let (mut socket, computer) = hello(PROTOCOL_VERSION, Role::Ser, &Capability::ALL, token);
+        assert!(matches!(
+            handshake(&mut socket, Some("secret")),
+            Err(MinecraftError::Unauthorized)
+        ));
+        drop(socket);
+        assert!(matches!(
+            computer.join().unwrap(),
+            Handshake::Refused { .. }
+        ));
+    }
+
+    let (mut socket, computer) = hello(1, Role::Ser, &Capability::ALL, None);
+    assert!(matches!(
+        handshake(&mut socket, None),
+        Err(MinecraftError::Incompatible { theirs: 1, .. })
+    ));
+    drop(socket);
+    assert!(matches!(
+        computer.join().unwrap(),
+        Handshake::Refused { .. }
+    ));
+
+    let (mut socket, computer) = hello(PROTOCOL_VERSION, Role::De, &[Capability::Fill], None);
+    assert!(matches!(
+        handshake(&mut socket, None),
+        Err(MinecraftError::Unsupported(Capability::Read))
+    ));
+    drop(socket);
+    assert!(matches!(
+        computer.join().unwrap(),
+        Handshake::Refused { .. }
+    ));
+
+    let (mut socket, computer) = hello(
+        PROTOCOL_VERSION,
+        Role::Ser,
+        &[Capability::Fill],
+        Some("secret"),
+    );
+    let peer = handshake(&mut socket, Some("secret")).unwrap();
+    assert_eq!(peer.role, Role::Ser);
+    assert_eq!(peer.cursor(BuildConfig::default()), Some(3));
+    let elsewhere = BuildConfig::new().with_origin(Position::new(100, 64, 0));
+    assert_eq!(peer.cursor(elsewhere), None);
+
+    let mut serializer = MinecraftSerializer::new(socket)
+        .with_peer(&peer)
+        .with_verify(true);
+    assert!(matches!(
+        1u8.serialize(&mut serializer),
+        Err(MinecraftError::Unsupported(Capability::Read))
+    ));
+    drop(serializer);
+    assert_eq!(
+        computer.join().unwrap(),
+        Handshake::Welcome {
+            version: PROTOCOL_VERSION,
+            capabilities: vec![Capability::Fill],
+        }
+    );
+}
@@ -0,0 +1,300 @@
+//! A [`Transport`] that runs commands on a vanilla server over RCON, for servers without
+//! ComputerCraft.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    Command, ForceloadAction, MinecraftBlock, MinecraftError, MinecraftResult, Position, Reply,
+    Request, Response, Transport,
+};
+
+/// Packet types of the RCON protocol.
+const LOGIN: i32 = 3;
+const EXEC: i32 = 2;
+
+/// The name reported for a block that is neither air nor one this crate places.
+pub const UNKNOWN_BLOCK: &str = "unknown";
+
+/// The output of a command that touched a position in a chunk that is not loaded.
+const UNLOADED: &str = "That position is not loaded";
+
+/// A logged-in RCON connection, carrying out requests with `setblock`, `fill`, `forceload` and
+/// `execute if block` instead of sending them to a computer.
+///
+/// There is no handshake over RCON; pass it straight to [`crate::MinecraftSerializer::new`] or
+/// [`crate::MinecraftDeserializer::new`]. Reading a block tests it against every block this crate
+/// places, so reads take many commands each.
+pub struct Rcon {
+    stream: TcpStream,
+    id: i32,
+
+    /// The dimension later commands run in, if one was chosen.
+    dimension: Option<String>,
+    responses: VecDeque<Response>,
+}
+
+impl Rcon {
+    /// Log in to the RCON port of a server, `rcon.port` in its `server.properties`, with its
+    /// `rcon.password`.
+    pub fn connect(address: impl ToSocketAddrs, password: &str) -> MinecraftResult<Self> {
+        let mut rcon = Self {
+            stream: TcpStream::connect(address)?,
+            id: 0,
+            dimension: None,
+            responses: VecDeque::new(),
+        };
+
+        let id = rcon.next_id();
+        write_packet(&mut rcon.stream, id, LOGIN, password)?;
+        let (answered, _, _) = read_packet(&mut rcon.stream)?;
+        if answered != id {
+            return Err(MinecraftError::Rcon("the password was refused".to_string()));
+        }
+        Ok(rcon)
+    }
+
+    /// Run `command` on the server and return its output.
+    pub fn command(&mut self, command: &str) -> MinecraftResult<String> {
+        let id = self.next_id();
+        write_packet(&mut self.stream, id, EXEC, command)?;
+
+        let (answered, _, output) = read_packet(&mut self.stream)?;
+        if answered != id {
+            return Err(MinecraftError::Rcon(format!(
+                "expected the output of command {id}, got {answered}"
+            )));
+        }
+        Ok(output)
+    }
+
+    fn next_id(&mut self) -> i32 {
+        self.id += 1;
+        self.id
+    }
+
+    /// Run `command` in the chosen dimension.
+    fn run(&mut self, command: &str) -> MinecraftResult<String> {
+        match &self.dimension {
+            Some(dimension) => self.command(&format!("execute in {dimension} run {command}")),
+            None => self.command(command),
+        }
+    }
+
+    /// Whether the block at `position` is `name`, or `None` if its chunk is not loaded.
+    fn test(&mut self, position: Position, name: &str) -> MinecraftResult<Option<bool>> {
+        let Position { x, y, z } = position;
+        let test = format!("if block {x} {y} {z} {name}");
+        let output = match &self.dimension {
+            Some(dimension) => self.command(&format!("execute in {dimension} {test}"))?,
+            None => self.command(&format!("execute {test}"))?,
+        };
+
+        match output.as_str() {
+            UNLOADED => Ok(None),
+            output => Ok(Some(output.starts_with("Test passed"))),
+        }
+    }
+
+    fn reply(&mut self, command: Command) -> MinecraftResult<Reply> {
+        Ok(match command {
+            Command::Dimension { id } => {
+                self.dimension = Some(id);
+                Reply::Done
+            }
+            // Nothing is kept between sessions to resume from.
+            Command::Begin { .. } | Command::Complete { .. } => Reply::Done,
+            Command::Forceload { action, x, z } => {
+                let action = match action {
+                    ForceloadAction::Add => "add",
+                    ForceloadAction::Remove => "remove",
+                };
+                self.run(&format!("forceload {action} {x} {z}"))?;
+                Reply::Done
+            }
+            Command::Read { position } => {
+                let candidates = MinecraftBlock::ALL.iter().map(ToString::to_string);
+                let mut name = UNKNOWN_BLOCK.to_string();
+                for candidate in std::iter::once("minecraft:air".to_string()).chain(candidates) {
+                    match self.test(position, &candidate)? {
+                        None => return Ok(Reply::Unloaded),
+                        Some(true) => {
+                            name = candidate;
+                            break;
+                        }
+                        Some(false) => {}
+                    }
+                }
+                Reply::Block { name }
+            }
+            Command::Place { position, block } => {
+                let Position { x, y, z } = position;
+                let output = self.run(&format!("setblock {x} {y} {z} {block}"))?;
+
+                // `setblock` fails when the block is already there, which is as good as placed.
+                if output.starts_with("Changed the block")
+                    || self.test(position, &block)? == Some(true)
+                {
+                    Reply::Placed { position }
+                } else {
+                    Reply::Failed { message: output }
+                }
+            }
+            Command::Fill { from, to, block } => {
+                let output = self.run(&format!(
+                    "fill {} {} {} {} {} {} {block}",
+                    from.x, from.y, from.z, to.x, to.y, to.z
+                ))?;
+
+                if output.starts_with("Successfully filled") || output == "No blocks were filled" {
+                    Reply::Done
+                } else {
+                    Reply::Error { message: output }
+                }
+            }
+        })
+    }
+}
+
+impl Transport for Rcon {
+    fn send_frame(&mut self, frame: String) -> MinecraftResult<()> {
+        let request: Request = serde_json::from_str(&frame)?;
+        let reply = self.reply(request.command)?;
+        self.responses.push_back(Response {
+            seq: request.seq,
+            cursor: request.cursor,
+            reply,
+        });
+        Ok(())
+    }
+
+    fn receive_frame(&mut self) -> MinecraftResult<String> {
+        let response = self
+            .responses
+            .pop_front()
+            .ok_or_else(|| MinecraftError::Rcon("no request to answer".to_string()))?;
+        Ok(serde_json::to_string(&response)?)
+    }
+}
+
+/// Write an RCON packet: its length, id, type and NUL-terminated body, followed by an empty
+/// NUL-terminated string.
+fn write_packet(stream: &mut impl Write, id: i32, kind: i32, body: &str) -> io::Result<()> {
+    let mut packet = Vec::with_capacity(body.len() + 14);
+    packet.extend(((body.len() + 10) as i32).to_le_bytes());
+    packet.extend(id.to_le_bytes());
+    packet.extend(kind.to_le_bytes());
+    packet.extend(body.as_bytes());
+    packet.extend([0, 0]);
+    stream.write_all(&packet)
+}
+
+/// Read an RCON packet, returning its id, type and body.
+fn read_packet(stream: &mut impl Read) -> io::Result<(i32, i32, String)> {
+    let mut int = [0; 4];
+    stream.read_exact(&mut int)?;
+    let len = i32::from_le_bytes(int);
+    if !(10..=4096 + 10).contains(&len) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("RCON packet of {len} bytes"),
+        ));
+    }
+
+    let mut packet = vec![0; len as usize];
+    stream.read_exact(&mut packet)?;
+    let id = i32::from_le_bytes(packet[0..4].try_into().unwrap());
+    let kind = i32::from_le_bytes(packet[4..8].try_into().unwrap());
+    let body = String::from_utf8_lossy(&packet[8..packet.len() - 2]).into_owned();
+    Ok((id, kind, body))
+}
+
+#[test]
+fn rcon_test() {
+    use crate::{MinecraftDeserializer, MinecraftSerializer, Overwrite};
+    use serde::{Deserialize, Serialize};
+    use std::{collections::HashMap, net::TcpListener, thread};
+
+    // A vanilla server with `password`, answering the commands of `connections` connections in
+    // turn.
+    let server = |connections: usize, mut world: HashMap<Position, String>| {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let handle = thread::spawn(move || {
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let (id, _, password) = read_packet(&mut stream).unwrap();
+                let id = if password == "password" { id } else { -1 };
+                write_packet(&mut stream, id, EXEC, "").unwrap();
+
+                while let Ok((id, _, command)) = read_packet(&mut stream) {
+                    let command = command
+                        .strip_prefix("execute in minecraft:overworld run ")
+                        .unwrap_or(&command);
+                    let words: Vec<&str> = command.split(' ').collect();
+                    let at = |i: usize| {
+                        let n = |j: usize| words[j].parse().unwrap();
+                        Position::new(n(i), n(i + 1), n(i + 2))
+                    };
+                    let name = |position| {
+                        world
+                            .get(&position)
+                            .cloned()
+                            .unwrap_or_else(|| "minecraft:air".to_string())
+                    };
+
+                    let output = match words[..] {
+                        ["setblock", .., block] if name(at(1)) == block => {
+                            "Could not set the block".to_string()
+                        }
+                        ["setblock", .., block] => {
+                            world.insert(at(1), block.to_string());
+                            "Changed the block".to_string()
+                        }
+                        ["execute", .., "if", "block", _, _, _, block] => {
+                            let at = at(words.len() - 4);
+                            match name(at) == block {
+                                true => "Test passed".to_string(),
+                                false => "Test failed".to_string(),
+                            }
+                        }
+                        _ => String::new(),
+                    };
+                    write_packet(&mut stream, id, 0, &output).unwrap();
+                }
+            }
+            world
+        });
+        (address, handle)
+    };
+
+    let value = ("rcon".to_string(), Some(7u8));
+    let (address, handle) = server(2, HashMap::new());
+    assert!(matches!(
+        Rcon::connect(address, "guess"),
+        Err(MinecraftError::Rcon(_))
+    ));
+
+    let rcon = Rcon::connect(address, "password").unwrap();
+    let mut serializer = MinecraftSerializer::new(rcon)
+        .with_overwrite(Overwrite::Air)
+        .with_verify(true);
+    value.serialize(&mut serializer).unwrap();
+    serializer.finish().unwrap();
+    drop(serializer);
+    let world = handle.join().unwrap();
+
+    let (address, handle) = server(1, world);
+    let rcon = Rcon::connect(address, "password").unwrap();
+    let mut deserializer = MinecraftDeserializer::new(rcon);
+    assert_eq!(
+        <(String, Option<u8>)>::deserialize(&mut deserializer).unwrap(),
+        value
+    );
+    drop(deserializer);
+    handle.join().unwrap();
+}
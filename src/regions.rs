@@ -0,0 +1,296 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BuildConfig, Chunks, MinecraftDeserializer, MinecraftError, MinecraftResult,
+    MinecraftSerializer, Position, layout::ORIGIN,
+};
+
+/// The part of the world reserved for one build.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    pub name: String,
+    pub build: BuildConfig,
+
+    /// Opposite corners of the bounding box, both included.
+    pub min: Position,
+    pub max: Position,
+
+    /// How many blocks the build is made of.
+    pub len: usize,
+}
+
+impl Region {
+    /// Whether `position` lies within the bounding box of the region.
+    pub fn contains(&self, position: Position) -> bool {
+        (self.min.x..=self.max.x).contains(&position.x)
+            && (self.min.y..=self.max.y).contains(&position.y)
+            && (self.min.z..=self.max.z).contains(&position.z)
+    }
+
+    /// Whether the region comes closer than `gap` blocks to the box from `min` to `max`.
+    fn overlaps(&self, min: Position, max: Position, gap: i32) -> bool {
+        let apart = |a_min: i32, a_max: i32, b_min: i32, b_max: i32| {
+            a_max + gap < b_min || b_max + gap < a_min
+        };
+
+        !(apart(self.min.x, self.max.x, min.x, max.x)
+            || apart(self.min.y, self.max.y, min.y, max.y)
+            || apart(self.min.z, self.max.z, min.z, max.z))
+    }
+}
+
+/// Hands out non-overlapping regions of the world to builds, and remembers them in a manifest
+/// file so that later sessions keep clear of them.
+#[derive(Debug)]
+pub struct Regions {
+    path: PathBuf,
+    start: Position,
+    gap: i32,
+    regions: Vec<Region>,
+}
+
+impl Regions {
+    /// Open the manifest at `path`, starting an empty one if it does not exist yet.
+    pub fn open(path: impl Into<PathBuf>) -> MinecraftResult<Self> {
+        let path = path.into();
+        let regions = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Self {
+            path,
+            start: ORIGIN,
+            gap: 1,
+            regions,
+        })
+    }
+
+    /// Look for free space eastwards from `start`.
+    pub fn with_start(mut self, start: Position) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Keep at least `gap` blocks of air between regions.
+    pub fn with_gap(mut self, gap: i32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Region> {
+        self.regions.iter().find(|r| r.name == name)
+    }
+
+    /// Reserve a region for `value` and return the build configuration that places it there.
+    ///
+    /// `configure` sets the serializer up exactly as it will be for the real build; its layout
+    /// and build configuration decide the size of the region, but its origin is replaced.
+    pub fn allocate<T, C>(
+        &mut self,
+        name: &str,
+        value: &T,
+        configure: C,
+    ) -> MinecraftResult<BuildConfig>
+    where
+        T: Serialize + ?Sized,
+        C: FnOnce(MinecraftSerializer) -> MinecraftSerializer,
+    {
+        if self.get(name).is_some() {
+            return Err(MinecraftError::DuplicateRegion(name.to_string()));
+        }
+
+        let plan = configure(MinecraftSerializer::dry_run());
+        let build = plan.build();
+        let relative = build
+            .with_origin(Position::default())
+            .with_chunks(Chunks::Any);
+
+        let mut plan = plan.with_build(relative);
+        value.serialize(&mut plan)?;
+
+        let placed = plan.placed();
+        let corner = |pick: fn(i32, i32) -> i32| {
+            placed.iter().map(|(p, _)| *p).fold(placed[0].0, |a, b| {
+                Position::new(pick(a.x, b.x), pick(a.y, b.y), pick(a.z, b.z))
+            })
+        };
+        let (min, max) = (corner(i32::min), corner(i32::max));
+
+        let mut origin = self.start;
+        while let Some(taken) = self.regions.iter().find(|r| {
+            r.build.dimension == build.dimension && r.overlaps(origin + min, origin + max, self.gap)
+        }) {
+            origin.x = taken.max.x + self.gap + 1 - min.x;
+        }
+
+        let region = Region {
+            name: name.to_string(),
+            build: build.with_origin(origin),
+            min: origin + min,
+            max: origin + max,
+            len: placed.len(),
+        };
+        self.regions.push(region.clone());
+        self.save()?;
+
+        Ok(region.build)
+    }
+
+    /// Erase the build in the region called `name` and release the region.
+    ///
+    /// `deserializer` must be set up with the build's layout. Unless the layout follows the shape
+    /// of the value, the recorded length of the build decides what is cleared, so nothing is read
+    /// and a damaged build is cleared all the same; otherwise the build is read to its end first.
+    /// Nothing is cleared if any block would lie outside the region.
+    pub fn erase(
+        &mut self,
+        name: &str,
+        deserializer: MinecraftDeserializer,
+    ) -> MinecraftResult<Option<Region>> {
+        let Some(region) = self.get(name) else {
+            return Ok(None);
+        };
+
+        deserializer.with_build(region.build).erase_region(region)?;
+        self.free(name)
+    }
+
+    /// Release the region of an erased build.
+    pub fn free(&mut self, name: &str) -> MinecraftResult<Option<Region>> {
+        let Some(i) = self.regions.iter().position(|r| r.name == name) else {
+            return Ok(None);
+        };
+
+        let region = self.regions.remove(i);
+        self.save()?;
+        Ok(Some(region))
+    }
+
+    fn save(&self) -> MinecraftResult<()> {
+        let file = BufWriter::new(File::create(&self.path)?);
+        serde_json::to_writer_pretty(file, &self.regions)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn allocate_test() {
+    use crate::{Grid, Line};
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("regions.json");
+
+    let mut regions = Regions::open(&path).unwrap();
+    let first = regions
+        .allocate("first", &vec![1u8; 20], |s| {
+            s.with_layout(Grid { width: 8 })
+        })
+        .unwrap();
+    let second = regions
+        .allocate("second", "hello", |s| s.with_layout(Line))
+        .unwrap();
+    assert!(matches!(
+        regions.allocate("second", "again", |s| s),
+        Err(MinecraftError::DuplicateRegion(_))
+    ));
+
+    let (a, b) = (
+        regions.get("first").unwrap(),
+        regions.get("second").unwrap(),
+    );
+    assert_eq!(first.origin, ORIGIN);
+    assert_eq!(a.max.x, ORIGIN.x + 7);
+    assert_eq!(second.origin.x, a.max.x + 2);
+    assert!(!a.overlaps(b.min, b.max, 1));
+
+    let mut reopened = Regions::open(&path).unwrap();
+    assert_eq!(reopened.regions(), regions.regions());
+
+    reopened.free("first").unwrap();
+    let third = reopened
+        .allocate("third", &[0u8; 2], |s| s.with_layout(Line))
+        .unwrap();
+    assert_eq!(third.origin, ORIGIN);
+}
+
+#[test]
+fn erase_region_test() {
+    use crate::{Line, mock};
+    use serde::Serialize;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut regions = Regions::open(dir.path().join("regions.json")).unwrap();
+    let value = "damaged".to_string();
+    let build = regions
+        .allocate("damaged", &value, |s| s.with_layout(Line))
+        .unwrap();
+
+    let mut placed = mock::place_with(
+        |s| s.with_layout(Line).with_build(build),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+
+    // Someone already broke the header block.
+    placed.remove(0);
+    let (region, world) =
+        mock::give_world(placed, |d| regions.erase("damaged", d.with_layout(Line))).unwrap();
+
+    assert_eq!(region.unwrap().name, "damaged");
+    assert!(world.is_empty());
+    assert!(regions.get("damaged").is_none());
+}
+
+#[test]
+fn erase_shaped_region_test() {
+    use crate::{Grid, Line, Table, mock};
+    use serde::Serialize;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut regions = Regions::open(dir.path().join("regions.json")).unwrap();
+    let value = vec![vec![1u8, 2], vec![3, 4, 5]];
+    let build = regions
+        .allocate("table", &value, |s| s.with_layout(Table::new()))
+        .unwrap();
+    let placed = mock::place_with(
+        |s| s.with_layout(Table::new()).with_build(build),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+
+    // A table is only cleared where the value put its blocks, not along a line by its length.
+    let (_, world) = mock::give_world(placed, |d| {
+        regions.erase("table", d.with_layout(Table::new()))
+    })
+    .unwrap();
+    assert!(world.is_empty());
+
+    let build = regions
+        .allocate("grid", &value, |s| s.with_layout(Grid { width: 2 }))
+        .unwrap();
+    let placed = mock::place_with(
+        |s| s.with_layout(Grid { width: 2 }).with_build(build),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+
+    // With the wrong layout the blocks would lie outside the region, so nothing is cleared.
+    let len = placed.len();
+    let (erased, world) =
+        mock::give_world(placed, |d| Ok(regions.erase("grid", d.with_layout(Line)))).unwrap();
+    assert!(matches!(erased, Err(MinecraftError::OutsideRegion { .. })));
+    assert_eq!(world.len(), len);
+    assert!(regions.get("grid").is_some());
+}
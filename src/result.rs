@@ -1,6 +1,8 @@
+use std::fmt::Display;
+
 use thiserror::Error;
 
-use crate::MinecraftBlock;
+use crate::{Capability, EnumRepr, MinecraftBlock, Position, Reply};
 
 pub type MinecraftResult<T> = Result<T, MinecraftError>;
 
@@ -13,6 +15,12 @@ pub enum MinecraftError {
     #[error("WebSocket error occurred")]
     WebSocket(#[from] Box<tungstenite::Error>),
 
+    #[error("RCON error: {0}")]
+    Rcon(String),
+
+    #[error("Bedrock client error: {0}")]
+    Bedrock(String),
+
     #[error("Failed to send message over WebSocket: {message}")]
     WebSocketSend {
         message: String,
@@ -27,6 +35,44 @@ pub enum MinecraftError {
         source: Box<tungstenite::Error>,
     },
 
+    #[error("JSON error occurred")]
+    Json(#[from] serde_json::Error),
+
+    // Protocol errors
+    #[error(
+        "Lost sync with the computer: request {seq} at block {cursor} was answered as request {found_seq} at block {found_cursor}"
+    )]
+    Desync {
+        seq: u64,
+        cursor: usize,
+        found_seq: u64,
+        found_cursor: usize,
+    },
+
+    #[error("The computer speaks protocol version {theirs}, but only version {ours} is supported")]
+    Incompatible { ours: u32, theirs: u32 },
+
+    #[error("The computer does not support {0:?}")]
+    Unsupported(Capability),
+
+    #[error("Shard {shard} of the build failed: {source}")]
+    ShardFailed {
+        shard: usize,
+        source: Box<MinecraftError>,
+    },
+
+    #[error("The computer did not present the shared token")]
+    Unauthorized,
+
+    #[error("Handshake failed: {0}")]
+    Handshake(String),
+
+    #[error("The computer could not carry out a request: {0}")]
+    Computer(String),
+
+    #[error("Unexpected reply from the computer: {0:?}")]
+    UnexpectedReply(Reply),
+
     // Parsing and conversion errors
     #[error("Failed to parse integer")]
     ParseInt(#[from] std::num::ParseIntError),
@@ -47,6 +93,9 @@ pub enum MinecraftError {
     #[error("Unknown Minecraft block type: '{0}'")]
     UnknownBlockType(String),
 
+    #[error("'{name}' already stands for {block} in the palette")]
+    PaletteClash { name: String, block: MinecraftBlock },
+
     #[error("Block cannot be converted to digit: {0}")]
     BlockToDigitConversion(String),
 
@@ -65,6 +114,21 @@ pub enum MinecraftError {
     #[error("Expected a digit block, found {0}")]
     NotDigitBlock(MinecraftBlock),
 
+    #[error("Placing a block at {position} failed: {message}")]
+    PlacementFailed { position: Position, message: String },
+
+    #[error("Block at {0} is in a chunk that is not loaded")]
+    UnloadedChunk(Position),
+
+    #[error("Block at {0} is outside of the configured chunks")]
+    OutsideChunks(Position),
+
+    #[error("Refusing to overwrite {}", occupied(.0))]
+    Occupied(Vec<(Position, String)>),
+
+    #[error("The build does not match the blocks that were placed: {}", join(.0))]
+    Mismatched(Vec<Mismatch>),
+
     // Serialization/Deserialization errors
     #[error("Serialization failed: {0}")]
     SerializationFailed(String),
@@ -91,6 +155,9 @@ pub enum MinecraftError {
     #[error("Invalid enum variant: '{0}'")]
     InvalidEnumVariant(String),
 
+    #[error("The enum variant was read without the parts {0:?} needs")]
+    VariantRepr(EnumRepr),
+
     #[error("Missing required field: '{0}'")]
     MissingField(String),
 
@@ -107,6 +174,13 @@ pub enum MinecraftError {
     #[error("Invalid protocol state")]
     InvalidProtocolState,
 
+    // Region errors
+    #[error("A region named '{0}' is already allocated")]
+    DuplicateRegion(String),
+
+    #[error("Erasing region '{name}' would clear {position}, outside of it")]
+    OutsideRegion { name: String, position: Position },
+
     // Generic error for backward compatibility
     #[error("{0}")]
     Custom(String),
@@ -115,6 +189,44 @@ pub enum MinecraftError {
     Placeholder,
 }
 
+/// A placed block that was not found where it should be when the build was read back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    /// Index of the block in the block stream.
+    pub index: usize,
+    pub position: Position,
+    pub expected: MinecraftBlock,
+
+    /// The name of the block found instead.
+    pub found: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "block {} at {} should be {} but is {}",
+            self.index, self.position, self.expected, self.found
+        )
+    }
+}
+
+fn occupied(blocks: &[(Position, String)]) -> String {
+    blocks
+        .iter()
+        .map(|(position, name)| format!("{name} at {position}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn join<T: Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl From<tungstenite::Error> for MinecraftError {
     fn from(err: tungstenite::Error) -> Self {
         MinecraftError::WebSocket(Box::new(err))
@@ -1,12 +1,14 @@
-use std::net::TcpStream;
-
 use serde::Serialize;
-use tungstenite::{Message, WebSocket};
 
 use crate::{
-    MinecraftError, MinecraftResult, NumberMarker,
+    BuildConfig, Capability, Command, EnumRepr, MinecraftError, MinecraftResult, Mismatch,
+    NumberMarker, Overwrite, Palette, Peer, Reply, Role, Transport,
     blocks::{BASE, MinecraftBlock},
+    chunks::Forceloaded,
+    layout::{Container, Layout, Position, Shape, Spiral},
     option_ser::OptionSerializer,
+    protocol::Channel,
+    value::{self, UNKNOWN_VARIANT_INDEX, UNKNOWN_VARIANT_NAME},
 };
 
 fn number_to_bits<V: Into<u128>>(value: V) -> MinecraftResult<Vec<MinecraftBlock>> {
@@ -28,23 +30,443 @@ fn number_to_bits<V: Into<u128>>(value: V) -> MinecraftResult<Vec<MinecraftBlock
     Ok(bits)
 }
 
+/// Something held back by canonical mode, replayed in order once the map ends.
+enum Placement {
+    Block(MinecraftBlock),
+    Shape(Shape),
+}
+
+impl Placement {
+    fn block(&self) -> Option<&MinecraftBlock> {
+        match self {
+            Placement::Block(block) => Some(block),
+            Placement::Shape(_) => None,
+        }
+    }
+}
+
+/// Map entries held back by canonical mode until the map ends.
+#[derive(Default)]
+struct CanonicalMap {
+    entries: Vec<(Vec<Placement>, Vec<Placement>)>,
+    in_value: bool,
+}
+
+impl CanonicalMap {
+    /// Where the next placement of the current entry goes.
+    fn current(&mut self) -> MinecraftResult<&mut Vec<Placement>> {
+        let (key, value) = self
+            .entries
+            .last_mut()
+            .ok_or(MinecraftError::InvalidProtocolState)?;
+
+        Ok(match self.in_value {
+            true => value,
+            false => key,
+        })
+    }
+}
+
 pub struct MinecraftSerializer {
-    socket: WebSocket<TcpStream>,
+    /// The computer placing the blocks, or `None` for a dry run.
+    channel: Option<Channel>,
+    canonical: bool,
+    enum_repr: EnumRepr,
+    maps: Vec<CanonicalMap>,
+    layout: Box<dyn Layout>,
+    build: BuildConfig,
+
+    /// Whether the computer has been told about `build` yet.
+    configured: bool,
+    forceloaded: Forceloaded,
+
+    /// Index of the next block to place.
+    index: usize,
+    placed: Vec<(Position, MinecraftBlock)>,
+
+    overwrite: Overwrite,
+
+    /// Blocks held back until [`MinecraftSerializer::finish`] has checked their targets.
+    pending: Vec<(Position, MinecraftBlock)>,
+    verify: bool,
+    retries: usize,
+
+    /// How many blocks are already in the world from an interrupted build.
+    resume: usize,
+
+    palette: Palette,
+
+    /// Index in the whole block stream of the first block placed, for a shard of a larger one.
+    offset: usize,
+
+    /// Whether the computer keeps count of the blocks placed, which a shard of a larger stream
+    /// must not.
+    counted: bool,
 }
 
 impl MinecraftSerializer {
-    pub fn new(socket: WebSocket<TcpStream>) -> Self {
-        Self { socket }
+    /// Place blocks through `transport`, usually a websocket a computer has opened.
+    pub fn new(transport: impl Transport + 'static) -> Self {
+        Self::with_channel(Some(Channel::new(transport)))
     }
 
-    /// Place a single block in the Minecraft world.
+    /// Connect to a serializing computer waiting at `url` (`ws://` or `wss://`), such as one
+    /// behind a relay, and go through the handshake with it, refusing it unless it presents
+    /// `token` when one is given.
+    pub fn connect(url: &str, token: Option<&str>) -> MinecraftResult<Self> {
+        let (socket, peer) = crate::transport::connect(url, Role::Ser, token)?;
+        Ok(Self::new(socket).with_peer(&peer))
+    }
+
+    /// A serializer that places nothing, and only works out where every block would go.
+    pub fn dry_run() -> Self {
+        Self::with_channel(None)
+    }
+
+    pub(crate) fn is_dry_run(&self) -> bool {
+        self.channel.is_none()
+    }
+
+    fn with_channel(channel: Option<Channel>) -> Self {
+        Self {
+            channel,
+            canonical: false,
+            enum_repr: EnumRepr::default(),
+            maps: Vec::new(),
+            layout: Box::new(Spiral),
+            build: BuildConfig::default(),
+            configured: false,
+            forceloaded: Forceloaded::default(),
+            index: 0,
+            placed: Vec::new(),
+            overwrite: Overwrite::default(),
+            pending: Vec::new(),
+            verify: false,
+            retries: 0,
+            resume: 0,
+            palette: Palette::default(),
+            offset: 0,
+            counted: true,
+        }
+    }
+
+    /// Choose which blocks a build may replace.
+    ///
+    /// Unless anything may be replaced, nothing is placed until [`MinecraftSerializer::finish`]
+    /// has checked every target position.
+    pub fn with_overwrite(mut self, overwrite: Overwrite) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    /// Only use what a computer that completed the [`crate::handshake`] supports.
+    pub fn with_peer(mut self, peer: &Peer) -> Self {
+        if let Some(channel) = &mut self.channel {
+            channel.set_peer(peer);
+        }
+        self
+    }
+
+    /// The computer placing the blocks, if it went through the [`crate::handshake`].
+    pub fn peer(&self) -> Option<&Peer> {
+        self.channel.as_ref().and_then(Channel::peer)
+    }
+
+    /// Carry on with a build that was interrupted after its first `placed` blocks.
+    ///
+    /// The value is serialized from the start again, but blocks before `placed` are taken as
+    /// already there. Resume from [`MinecraftSerializer::placed`] of the interrupted serializer,
+    /// or from the [`Peer::cursor`] the computer reported for this build after a restart.
+    pub fn with_resume(mut self, placed: usize) -> Self {
+        self.resume = placed;
+        self
+    }
+
+    /// Place the blocks of `palette` instead of the default ones; the build must be read back with
+    /// the same palette.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    /// Try a placement the computer reports as failed up to `retries` more times.
+    pub fn with_retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Read the build back in [`MinecraftSerializer::finish`] and compare it with the blocks
+    /// that were placed.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Complete a build once the whole value has been serialized.
+    ///
+    /// Blocks held back by the [`Overwrite`] setting are placed, unless it refuses any target, in
+    /// which case nothing is placed and the error lists every offending position. With
+    /// [`MinecraftSerializer::with_verify`], every placed block is then read back and any that
+    /// is missing is reported by its index and position.
+    pub fn finish(&mut self) -> MinecraftResult<()> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut occupied = Vec::new();
+        for (position, _) in &pending {
+            let name = self.query(*position)?;
+            let block = self.palette.block(&name).map(|b| b.to_string());
+            if !self.overwrite.allows(
+                self.build.dimension,
+                *position,
+                block.as_deref().unwrap_or(&name),
+            ) {
+                occupied.push((*position, name));
+            }
+        }
+
+        if !occupied.is_empty() {
+            return Err(MinecraftError::Occupied(occupied));
+        }
+
+        for (position, block) in pending {
+            self.place_at(position, block)?;
+        }
+
+        if self.verify && self.channel.is_some() {
+            self.check_placed()?;
+        }
+
+        if !self.configured {
+            self.configure()?;
+        }
+        if self.counts() {
+            self.call(Command::Complete {
+                build: self.build.key(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Read every placed block back, failing with every one that is not there.
+    fn check_placed(&mut self) -> MinecraftResult<()> {
+        let mut mismatches = Vec::new();
+        for index in 0..self.placed.len() {
+            let (position, expected) = self.placed[index].clone();
+            let found = self.query(position)?;
+
+            if found != self.palette.name(&expected) {
+                mismatches.push(Mismatch {
+                    index: self.offset + index,
+                    position,
+                    expected,
+                    found,
+                });
+            }
+        }
+
+        match mismatches.is_empty() {
+            true => Ok(()),
+            false => Err(MinecraftError::Mismatched(mismatches)),
+        }
+    }
+
+    /// Choose where blocks are placed, [`Spiral`] by default.
+    pub fn with_layout<L: Layout + 'static>(mut self, layout: L) -> Self {
+        self.layout = Box::new(layout);
+        self
+    }
+
+    pub(crate) fn with_boxed_layout(mut self, layout: Box<dyn Layout>) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Choose where and how the build is placed in the world, see [`BuildConfig`].
+    pub fn with_build(mut self, build: BuildConfig) -> Self {
+        self.build = build;
+        self
+    }
+
+    /// The position the next block will be placed at.
+    pub fn position(&self) -> Position {
+        self.build.place(self.layout.position(self.index))
+    }
+
+    /// Where and how the build is placed in the world.
+    pub fn build(&self) -> BuildConfig {
+        self.build
+    }
+
+    /// Every block placed so far, in order and with its position.
+    pub fn placed(&self) -> &[(Position, MinecraftBlock)] {
+        &self.placed
+    }
+
+    /// Make equal values always produce identical block streams.
+    ///
+    /// Map entries are sorted by their encoded key blocks and every NaN is written the same way.
+    pub fn with_canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Choose how enum variants are identified, see [`EnumRepr`].
+    pub fn with_enum_repr(mut self, enum_repr: EnumRepr) -> Self {
+        self.enum_repr = enum_repr;
+        self
+    }
+
+    /// Place a single block in the Minecraft world, or buffer it while a canonical map is open.
     pub(super) fn place_block(&mut self, block: MinecraftBlock) -> Result<(), MinecraftError> {
-        let message = Message::text(block.to_string());
-        self.socket.send(message)?;
-        self.socket.read()?;
+        if let Some(map) = self.maps.last_mut() {
+            map.current()?.push(Placement::Block(block));
+            return Ok(());
+        }
+
+        let position = self.position();
+        self.put(position, block)
+    }
+
+    /// Place the next block of the stream at `position`, unless it is there from an interrupted
+    /// build or must wait for [`MinecraftSerializer::finish`].
+    fn put(&mut self, position: Position, block: MinecraftBlock) -> MinecraftResult<()> {
+        self.index += 1;
+
+        if self.index <= self.resume {
+            self.placed.push((position, block));
+            return Ok(());
+        }
+
+        if self.overwrite == Overwrite::Anything || self.channel.is_none() {
+            return self.place_at(position, block);
+        }
+
+        self.pending.push((position, block));
         Ok(())
     }
 
+    /// Place `blocks`, the part of another serializer's block stream starting at index `offset`,
+    /// at the positions given.
+    pub(crate) fn place_shard(
+        &mut self,
+        offset: usize,
+        blocks: &[(Position, MinecraftBlock)],
+    ) -> MinecraftResult<()> {
+        self.offset = offset;
+        self.counted = false;
+        blocks
+            .iter()
+            .try_for_each(|(position, block)| self.put(*position, block.clone()))
+    }
+
+    fn place_at(&mut self, position: Position, block: MinecraftBlock) -> MinecraftResult<()> {
+        self.prepare(position)?;
+
+        let mut retries = self.retries;
+        while self.channel.is_some() {
+            let place = Command::Place {
+                position,
+                block: self.palette.name(&block),
+            };
+            let message = match self.call(place)? {
+                Reply::Placed { position: at } if at == position => break,
+                Reply::Placed { position: at } => format!("the block was placed at {at} instead"),
+                Reply::Failed { message } => message,
+                reply => return Err(MinecraftError::UnexpectedReply(reply)),
+            };
+
+            if retries == 0 {
+                return Err(MinecraftError::PlacementFailed { position, message });
+            }
+            retries -= 1;
+        }
+
+        self.placed.push((position, block));
+        Ok(())
+    }
+
+    /// Ask the computer for the name of the block at `position`.
+    fn query(&mut self, position: Position) -> MinecraftResult<String> {
+        self.prepare(position)?;
+        match self.call(Command::Read { position })? {
+            Reply::Block { name } => Ok(name),
+            Reply::Unloaded => Err(MinecraftError::UnloadedChunk(position)),
+            reply => Err(MinecraftError::UnexpectedReply(reply)),
+        }
+    }
+
+    /// Send a command to the computer and return its reply; a dry run is always done.
+    fn call(&mut self, command: Command) -> MinecraftResult<Reply> {
+        let cursor = self.placed.len();
+        match &mut self.channel {
+            Some(channel) => channel.call(cursor, command),
+            None => Ok(Reply::Done),
+        }
+    }
+
+    /// Make sure the computer can place a block at `position`.
+    fn prepare(&mut self, position: Position) -> MinecraftResult<()> {
+        if !self.configured {
+            self.configure()?;
+        }
+
+        for command in self.forceloaded.visit(self.build.chunks, position)? {
+            self.call(command)?;
+        }
+        Ok(())
+    }
+
+    /// Check that the computer can do everything this build asks for, and tell it the dimension.
+    fn configure(&mut self) -> MinecraftResult<()> {
+        self.configured = true;
+        let Some(channel) = &mut self.channel else {
+            return Ok(());
+        };
+
+        let mut required = Capability::required_by(self.build);
+        if self.overwrite != Overwrite::Anything || self.verify {
+            required.push(Capability::Read);
+        }
+        channel.require(&required)?;
+
+        if channel.supports(Capability::Dimension) {
+            let id = self.build.dimension.id().to_string();
+            self.call(Command::Dimension { id })?;
+        }
+
+        if self.counts() {
+            self.call(Command::Begin {
+                build: self.build.key(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Whether the computer should keep count of this build's blocks for a later resume.
+    fn counts(&self) -> bool {
+        self.counted
+            && self
+                .channel
+                .as_ref()
+                .is_some_and(|c| c.supports(Capability::Resume))
+    }
+
+    /// Report a step of the serde walk to the layout, or buffer it while a canonical map is open.
+    fn shape(&mut self, shape: Shape) -> MinecraftResult<()> {
+        match self.maps.last_mut() {
+            Some(map) => map.current()?.push(Placement::Shape(shape)),
+            None => self.layout.shape(self.index, shape),
+        }
+        Ok(())
+    }
+
+    /// Place buffered blocks and report buffered shapes in their original order.
+    fn replay(&mut self, placements: Vec<Placement>) -> MinecraftResult<()> {
+        placements.into_iter().try_for_each(|p| match p {
+            Placement::Block(block) => self.place_block(block),
+            Placement::Shape(shape) => self.shape(shape),
+        })
+    }
+
     /// Place multiple blocks in the Minecraft world by iterating over a vector of blocks.
     #[inline(always)]
     fn place_blocks(&mut self, blocks: Vec<MinecraftBlock>) -> Result<(), MinecraftError> {
@@ -70,6 +492,31 @@ impl MinecraftSerializer {
         self.place_block(marker)
     }
 
+    /// Write the identity of an enum variant as configured by [`EnumRepr`].
+    fn write_variant(&mut self, variant_index: u32, variant: &str) -> MinecraftResult<()> {
+        use serde::Serializer;
+
+        let missing = match self.enum_repr {
+            EnumRepr::Index => variant_index == UNKNOWN_VARIANT_INDEX,
+            EnumRepr::Name => variant == UNKNOWN_VARIANT_NAME,
+            EnumRepr::NameAndIndex => {
+                variant_index == UNKNOWN_VARIANT_INDEX || variant == UNKNOWN_VARIANT_NAME
+            }
+        };
+        if missing {
+            return Err(MinecraftError::VariantRepr(self.enum_repr));
+        }
+
+        match self.enum_repr {
+            EnumRepr::Index => self.serialize_u32(variant_index),
+            EnumRepr::Name => self.serialize_str(variant),
+            EnumRepr::NameAndIndex => {
+                self.serialize_str(variant)?;
+                self.serialize_u32(variant_index)
+            }
+        }
+    }
+
     /// Write a byte slice as pairs of blocks, each representing a byte split into two base-91 values.
     fn write_bytes(&mut self, v: &[u8]) -> MinecraftResult<()> {
         let mut blocks = Vec::with_capacity(2 * v.len());
@@ -145,12 +592,20 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
 
     #[inline]
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        let v = match self.canonical && v.is_nan() {
+            true => f32::NAN,
+            false => v,
+        };
         let bits = v.to_bits();
         self.serialize_number(bits, NumberMarker::F32)
     }
 
     #[inline]
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        let v = match self.canonical && v.is_nan() {
+            true => f64::NAN,
+            false => v,
+        };
         let bits = v.to_bits();
         self.serialize_number(bits, NumberMarker::F64)
     }
@@ -201,10 +656,10 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
         self.place_block(MinecraftBlock::OakLog)?;
-        variant_index.serialize(&mut *self)
+        self.write_variant(variant_index, variant)
     }
 
     fn serialize_newtype_struct<T>(
@@ -215,6 +670,10 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
     where
         T: ?Sized + serde::Serialize,
     {
+        if name == value::VARIANT_TOKEN {
+            return value::with_placeholders(|| value.serialize(self));
+        }
+
         self.place_block(MinecraftBlock::SpruceLog)?;
         name.serialize(&mut *self)?;
         value.serialize(&mut *self)
@@ -224,26 +683,28 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
         self.place_block(MinecraftBlock::DarkOakLog)?;
-        variant_index.serialize(&mut *self)?;
+        self.write_variant(variant_index, variant)?;
         value.serialize(self)
     }
 
     #[inline]
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
         self.place_block(MinecraftBlock::CherryLog)?;
+        self.shape(Shape::Open(Container::Seq))?;
         Ok(self)
     }
 
     #[inline]
     fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
         self.place_block(MinecraftBlock::CrimsonStem)?;
+        self.shape(Shape::Open(Container::Tuple))?;
         Ok(self)
     }
 
@@ -255,6 +716,7 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self.place_block(MinecraftBlock::WarpedStem)?;
         self.serialize_str(name)?;
         self.serialize_u32(len as u32)?;
+        self.shape(Shape::Open(Container::TupleStruct))?;
         Ok(self)
     }
 
@@ -262,17 +724,22 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
         self.place_block(MinecraftBlock::PurpurBlock)?;
-        self.serialize_u32(variant_index)?;
+        self.write_variant(variant_index, variant)?;
         self.serialize_u32(len as u32)?;
+        self.shape(Shape::Open(Container::TupleVariant))?;
         Ok(self)
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
         self.place_block(MinecraftBlock::PurpurPillar)?;
+        self.shape(Shape::Open(Container::Map))?;
+        if self.canonical {
+            self.maps.push(CanonicalMap::default());
+        }
         Ok(self)
     }
 
@@ -284,6 +751,7 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self.place_block(MinecraftBlock::GoldBlock)?;
         self.serialize_str(name)?;
         self.serialize_u32(len as u32)?;
+        self.shape(Shape::Open(Container::Struct))?;
         Ok(self)
     }
 
@@ -291,16 +759,25 @@ impl serde::ser::Serializer for &mut MinecraftSerializer {
         self,
         _name: &'static str,
         variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
         self.place_block(MinecraftBlock::DiamondBlock)?;
-        self.serialize_u32(variant_index)?;
+        self.write_variant(variant_index, variant)?;
         self.serialize_u32(len as u32)?;
+        self.shape(Shape::Open(Container::StructVariant))?;
         Ok(self)
     }
 }
 
+impl Drop for MinecraftSerializer {
+    fn drop(&mut self) {
+        for command in self.forceloaded.release() {
+            let _ = self.call(command);
+        }
+    }
+}
+
 impl serde::ser::SerializeSeq for &mut MinecraftSerializer {
     type Ok = ();
     type Error = MinecraftError;
@@ -310,12 +787,15 @@ impl serde::ser::SerializeSeq for &mut MinecraftSerializer {
     where
         T: ?Sized + serde::Serialize,
     {
+        self.shape(Shape::Element)?;
         value.serialize(&mut **self)
     }
 
     #[inline(always)]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.place_block(MinecraftBlock::DarkPrismarine)
+        self.shape(Shape::Element)?;
+        self.place_block(MinecraftBlock::DarkPrismarine)?;
+        self.shape(Shape::Close)
     }
 }
 
@@ -373,25 +853,48 @@ impl serde::ser::SerializeMap for &mut MinecraftSerializer {
     type Ok = ();
     type Error = MinecraftError;
 
-    #[inline(always)]
     fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
+        if self.canonical
+            && let Some(map) = self.maps.last_mut()
+        {
+            map.entries.push(Default::default());
+            map.in_value = false;
+        }
+        self.shape(Shape::Element)?;
         key.serialize(&mut **self)
     }
 
-    #[inline(always)]
     fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
     where
         T: ?Sized + serde::Serialize,
     {
+        if self.canonical
+            && let Some(map) = self.maps.last_mut()
+        {
+            map.in_value = true;
+        }
         value.serialize(&mut **self)
     }
 
-    #[inline(always)]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.place_block(MinecraftBlock::AmethystBlock)
+        if self.canonical
+            && let Some(mut map) = self.maps.pop()
+        {
+            map.entries.sort_by(|(a, _), (b, _)| {
+                let a = a.iter().filter_map(Placement::block);
+                a.cmp(b.iter().filter_map(Placement::block))
+            });
+            for (key, value) in map.entries {
+                self.replay(key)?;
+                self.replay(value)?;
+            }
+        }
+        self.shape(Shape::Element)?;
+        self.place_block(MinecraftBlock::AmethystBlock)?;
+        self.shape(Shape::Close)
     }
 }
 
@@ -404,13 +907,16 @@ impl serde::ser::SerializeStruct for &mut MinecraftSerializer {
     where
         T: ?Sized + serde::Serialize,
     {
-        <Self as serde::ser::SerializeMap>::serialize_key(self, key)?;
-        <Self as serde::ser::SerializeMap>::serialize_value(self, value)
+        self.shape(Shape::Element)?;
+        key.serialize(&mut **self)?;
+        value.serialize(&mut **self)
     }
 
     #[inline(always)]
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        self.place_block(MinecraftBlock::EmeraldBlock)
+        self.shape(Shape::Element)?;
+        self.place_block(MinecraftBlock::EmeraldBlock)?;
+        self.shape(Shape::Close)
     }
 }
 
@@ -429,3 +935,146 @@ impl serde::ser::SerializeStructVariant for &mut MinecraftSerializer {
         <Self as serde::ser::SerializeStruct>::end(self)
     }
 }
+
+#[test]
+fn canonical_map_test() {
+    use std::collections::HashMap;
+
+    let forward: HashMap<String, f64> = (0..32).map(|i| (format!("key{i}"), i as f64)).collect();
+    let mut backward: HashMap<String, f64> = (0..32)
+        .rev()
+        .map(|i| (format!("key{i}"), i as f64))
+        .collect();
+    backward.insert("nan".to_string(), -f64::NAN);
+
+    let mut forward = forward;
+    forward.insert("nan".to_string(), f64::NAN);
+
+    let canonical = |map: &HashMap<String, f64>| {
+        crate::mock::serialize_with(|s| s.with_canonical(true), |s| map.serialize(s)).unwrap()
+    };
+
+    let blocks = canonical(&forward);
+    assert_eq!(blocks, canonical(&backward));
+    assert_eq!(
+        crate::mock::deserialize::<HashMap<String, f64>>(blocks)
+            .unwrap()
+            .len(),
+        33
+    );
+}
+
+#[test]
+fn verify_test() {
+    use crate::{Line, mock};
+    use std::collections::HashMap;
+
+    let spawn = crate::layout::ORIGIN + Position::new(0, 0, -2);
+    let world = HashMap::from([(spawn, mock::PROTECTED.to_string())]);
+    let build = |verify| move |s: MinecraftSerializer| s.with_layout(Line).with_verify(verify);
+    let place = |s: &mut MinecraftSerializer| {
+        "verified".serialize(&mut *s)?;
+        s.finish()
+    };
+
+    mock::record_in(world.clone(), build(false), place).unwrap();
+    match mock::record_in(world, build(true), place) {
+        Err(MinecraftError::Mismatched(mismatches)) => {
+            assert_eq!(mismatches.len(), 1);
+            assert_eq!(mismatches[0].index, 2);
+            assert_eq!(mismatches[0].position, spawn);
+            assert_eq!(mismatches[0].found, mock::PROTECTED);
+        }
+        other => panic!("expected a mismatch, got {other:?}"),
+    }
+
+    mock::serialize_with(build(true), place).unwrap();
+}
+
+#[test]
+fn acknowledgement_test() {
+    use crate::{Line, mock};
+    use std::collections::HashMap;
+
+    let barrier = crate::layout::ORIGIN + Position::new(0, 0, -1);
+    let world = HashMap::from([(barrier, mock::REFUSED.to_string())]);
+    let value = "ack";
+
+    match mock::record_in(
+        world.clone(),
+        |s| s.with_layout(Line),
+        |s| value.serialize(s),
+    ) {
+        Err(MinecraftError::PlacementFailed { position, message }) => {
+            assert_eq!(position, barrier);
+            assert_eq!(message, "Could not set the block");
+        }
+        other => panic!("expected a failed placement, got {other:?}"),
+    }
+
+    let commands = mock::record_in(
+        world,
+        |s| s.with_layout(Line).with_retries(1),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+    let attempts = commands
+        .iter()
+        .filter(|c| matches!(c, Command::Place { position, .. } if *position == barrier));
+    assert_eq!(attempts.count(), 2);
+}
+
+#[test]
+fn resume_test() {
+    use crate::{Line, mock};
+
+    let value = ("resume", vec![Some(1u8), None]);
+    let full = mock::place_with(|s| s.with_layout(Line), |s| value.serialize(s)).unwrap();
+    let resumed = mock::place_with(
+        |s| s.with_layout(Line).with_resume(5),
+        |s| value.serialize(s),
+    )
+    .unwrap();
+
+    assert_eq!(resumed, full[5..]);
+}
+
+#[test]
+fn resume_cursor_test() {
+    use crate::{Line, mock};
+    use std::collections::HashMap;
+
+    let value = ("resume", vec![Some(1u8), None]);
+    let build = BuildConfig::default();
+    let resumed = |cursors: &HashMap<String, usize>| {
+        let placed = cursors.get(&build.key()).copied();
+        move |s: MinecraftSerializer| s.with_layout(Line).with_resume(placed.unwrap_or(0))
+    };
+
+    // The connection drops before `finish`, leaving the count of the blocks placed behind.
+    let (result, first, cursors) = mock::place_counted(
+        HashMap::new(),
+        |s| s.with_layout(Line),
+        |s| value.serialize(s),
+    );
+    result.unwrap();
+    assert_eq!(cursors, HashMap::from([(build.key(), first.len())]));
+
+    // The build carries on from that count and completes, which forgets it.
+    let (result, placed, cursors) = mock::place_counted(cursors.clone(), resumed(&cursors), |s| {
+        value.serialize(&mut *s)?;
+        s.finish()
+    });
+    result.unwrap();
+    assert!(placed.is_empty());
+    assert!(cursors.is_empty());
+
+    // So the next build on the same computer starts from the beginning.
+    let (result, placed, cursors) = mock::place_counted(cursors.clone(), resumed(&cursors), |s| {
+        value.serialize(&mut *s)?;
+        s.finish()
+    });
+    result.unwrap();
+    assert_eq!(placed, first);
+    assert!(cursors.is_empty());
+}
@@ -0,0 +1,392 @@
+use std::{
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use tungstenite::WebSocket;
+
+use crate::{
+    Computer, MinecraftDeserializer, MinecraftError, MinecraftResult, MinecraftSerializer, Peer,
+    Role, Site, handshake,
+};
+
+type SerHandler = dyn Fn(MinecraftSerializer) -> MinecraftResult<()> + Send + Sync;
+type DeHandler = dyn Fn(MinecraftDeserializer) -> MinecraftResult<()> + Send + Sync;
+
+/// How long a computer has to complete the handshake by default.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many sessions [`MinecraftServer::run`] serves at once by default.
+const MAX_SESSIONS: usize = 64;
+
+/// Listens for ComputerCraft computers and hands each one to the handler registered for its
+/// [`Role`], set up for the [`Site`] it belongs to.
+///
+/// ```no_run
+/// # use serdecraft::{MinecraftServer, MinecraftResult};
+/// # fn main() -> MinecraftResult<()> {
+/// MinecraftServer::bind("127.0.0.1:8765")?
+///     .serialize(vec![1, 2, 3])
+///     .deserialize(|value: Vec<u8>| println!("{value:?}"))
+///     .run()
+/// # }
+/// ```
+pub struct MinecraftServer {
+    listener: TcpListener,
+    routes: Routes,
+    max_sessions: usize,
+}
+
+/// Everything a session needs to find its handler and site, cloned into each session's thread.
+#[derive(Clone)]
+struct Routes {
+    ser: Option<Arc<SerHandler>>,
+    de: Option<Arc<DeHandler>>,
+    sites: Vec<(Computer, Site)>,
+    token: Option<String>,
+    handshake_timeout: Duration,
+}
+
+impl MinecraftServer {
+    pub fn bind(address: impl ToSocketAddrs) -> MinecraftResult<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(address)?,
+            routes: Routes {
+                ser: None,
+                de: None,
+                sites: Vec::new(),
+                token: None,
+                handshake_timeout: HANDSHAKE_TIMEOUT,
+            },
+            max_sessions: MAX_SESSIONS,
+        })
+    }
+
+    pub fn local_addr(&self) -> MinecraftResult<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Refuse every computer that does not present `token` in its handshake.
+    ///
+    /// The server does not accept TLS, so the token is sent in the clear unless a proxy in front
+    /// of it does.
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.routes.token = Some(token.into());
+        self
+    }
+
+    /// Hang up on a computer that has not completed the handshake within `timeout`, 10 seconds by
+    /// default.
+    pub fn with_handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.routes.handshake_timeout = timeout;
+        self
+    }
+
+    /// Hang up on computers that connect while [`MinecraftServer::run`] is already serving
+    /// `max_sessions` others, 64 by default.
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    /// Set up the sessions of `computer` for `site`; other computers use [`Site::default`].
+    pub fn with_site(mut self, computer: Computer, site: Site) -> Self {
+        self.routes.sites.push((computer, site));
+        self
+    }
+
+    /// Hand every serializing computer to `handler`, already set up for its site and for what it
+    /// supports.
+    pub fn on_ser<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(MinecraftSerializer) -> MinecraftResult<()> + Send + Sync + 'static,
+    {
+        self.routes.ser = Some(Arc::new(handler));
+        self
+    }
+
+    /// Hand every deserializing computer to `handler`, already set up for its site and for what
+    /// it supports.
+    pub fn on_de<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(MinecraftDeserializer) -> MinecraftResult<()> + Send + Sync + 'static,
+    {
+        self.routes.de = Some(Arc::new(handler));
+        self
+    }
+
+    /// Build `value` for every serializing computer.
+    pub fn serialize<T: Serialize + Send + Sync + 'static>(self, value: T) -> Self {
+        self.on_ser(move |mut serializer| {
+            value.serialize(&mut serializer)?;
+            serializer.finish()
+        })
+    }
+
+    /// Read a `T` from every deserializing computer and pass it to `callback`.
+    pub fn deserialize<T, F>(self, callback: F) -> Self
+    where
+        T: DeserializeOwned,
+        F: Fn(T) + Send + Sync + 'static,
+    {
+        self.on_de(move |mut deserializer| {
+            callback(T::deserialize(&mut deserializer)?);
+            Ok(())
+        })
+    }
+
+    /// Serve every computer that connects on its own thread, up to
+    /// [`MinecraftServer::with_max_sessions`] at once, until the listener fails.
+    ///
+    /// A session that fails is logged and does not affect the others.
+    pub fn run(&mut self) -> MinecraftResult<()> {
+        let active = Arc::new(AtomicUsize::new(0));
+        loop {
+            let (stream, address) = self.listener.accept()?;
+            if active.load(Ordering::SeqCst) >= self.max_sessions {
+                log::warn!("{address}: refused, already serving {}", self.max_sessions);
+                continue;
+            }
+
+            let slot = Slot::take(&active);
+            let routes = self.routes.clone();
+            thread::spawn(move || {
+                let _slot = slot;
+                match routes.session(stream, address) {
+                    Ok(()) => log::info!("{address}: session finished"),
+                    Err(e) => log::error!("{address}: session failed: {e}"),
+                }
+            });
+        }
+    }
+
+    /// Wait for `count` serializing computers, for a build shared between them with
+    /// [`crate::serialize_sharded`], instead of handing them to the serialization handler.
+    pub fn accept_serializers(
+        &mut self,
+        count: usize,
+    ) -> MinecraftResult<Vec<MinecraftSerializer>> {
+        (0..count)
+            .map(|_| {
+                let (stream, address) = self.listener.accept()?;
+                let (socket, peer, site) = self.routes.greet(stream, address)?;
+                if peer.role != Role::Ser {
+                    return Err(MinecraftError::Handshake(format!(
+                        "expected a Ser computer, found {peer} connected as {:?}",
+                        peer.role
+                    )));
+                }
+                Ok(site
+                    .serializer(MinecraftSerializer::new(socket))
+                    .with_peer(&peer))
+            })
+            .collect()
+    }
+
+    /// Serve the next computer that connects, returning how its session went.
+    pub fn accept(&mut self) -> MinecraftResult<()> {
+        let (stream, address) = self.listener.accept()?;
+        self.routes.session(stream, address)
+    }
+}
+
+/// One of the sessions [`MinecraftServer::run`] is serving, given back when dropped, even by a
+/// session that panicked.
+struct Slot(Arc<AtomicUsize>);
+
+impl Slot {
+    fn take(active: &Arc<AtomicUsize>) -> Self {
+        active.fetch_add(1, Ordering::SeqCst);
+        Self(active.clone())
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl Routes {
+    /// Go through the handshake with a computer that has connected, and find its site.
+    fn greet(
+        &self,
+        stream: TcpStream,
+        address: SocketAddr,
+    ) -> MinecraftResult<(WebSocket<TcpStream>, Peer, Site)> {
+        // A computer that stops talking must not hold on to its session before it is known.
+        stream.set_read_timeout(Some(self.handshake_timeout))?;
+        let mut socket =
+            tungstenite::accept(stream).map_err(|e| MinecraftError::Handshake(e.to_string()))?;
+        let peer = handshake(&mut socket, self.token.as_deref())?;
+        socket.get_ref().set_read_timeout(None)?;
+        log::info!(
+            "{address}: {peer} connected as {:?} with protocol {} and {:?}",
+            peer.role,
+            peer.version,
+            peer.capabilities
+        );
+
+        let site = self
+            .sites
+            .iter()
+            .find(|(computer, _)| computer.matches(&peer))
+            .map(|(_, site)| site.clone())
+            .unwrap_or_default();
+        Ok((socket, peer, site))
+    }
+
+    fn session(&self, stream: TcpStream, address: SocketAddr) -> MinecraftResult<()> {
+        let (socket, peer, site) = self.greet(stream, address)?;
+
+        match (peer.role, &self.ser, &self.de) {
+            (Role::Ser, Some(handler), _) => handler(
+                site.serializer(MinecraftSerializer::new(socket))
+                    .with_peer(&peer),
+            ),
+            (Role::De, _, Some(handler)) => handler(
+                site.deserializer(MinecraftDeserializer::new(socket))
+                    .with_peer(&peer),
+            ),
+            (role, _, _) => Err(MinecraftError::Handshake(format!(
+                "no handler for {role:?} computers"
+            ))),
+        }
+    }
+}
+
+#[test]
+fn server_test() {
+    use crate::mock;
+    use std::{collections::HashMap, sync::mpsc};
+
+    let value = vec![Some("served".to_string()), None];
+    let (sender, received) = mpsc::channel();
+    let mut server = MinecraftServer::bind("127.0.0.1:0")
+        .unwrap()
+        .serialize(value.clone())
+        .deserialize(move |value: Vec<Option<String>>| sender.send(value).unwrap());
+    let address = server.local_addr().unwrap();
+
+    let computer = mock::computer(address, Role::Ser, 1, HashMap::new());
+    server.accept().unwrap();
+    let world = computer.join().unwrap();
+
+    let computer = mock::computer(address, Role::De, 1, world);
+    server.accept().unwrap();
+    computer.join().unwrap();
+    assert_eq!(received.recv().unwrap(), value);
+
+    let mut server = MinecraftServer::bind("127.0.0.1:0").unwrap();
+    let computer = mock::computer(server.local_addr().unwrap(), Role::De, 1, HashMap::new());
+    assert!(matches!(server.accept(), Err(MinecraftError::Handshake(_))));
+    computer.join().unwrap();
+}
+
+#[test]
+fn site_test() {
+    use crate::{BuildConfig, Line, Position, mock};
+    use std::{collections::HashMap, sync::mpsc, time::Duration};
+
+    let value = "routed".to_string();
+    let first = BuildConfig::new().with_origin(Position::new(0, 64, 0));
+    let second = BuildConfig::new().with_origin(Position::new(500, 70, 500));
+    let mut server = MinecraftServer::bind("127.0.0.1:0")
+        .unwrap()
+        .with_site(Computer::Id(1), Site::new(first).with_layout(Line))
+        .with_site(Computer::Label("site-2".to_string()), Site::new(second))
+        .serialize(value.clone());
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    // A computer that never finishes its handshake must not hold up the others.
+    let _idle = TcpStream::connect(address).unwrap();
+
+    let (sender, built) = mpsc::channel();
+    for id in [1, 2] {
+        let sender = sender.clone();
+        let computer = mock::computer(address, Role::Ser, id, HashMap::new());
+        thread::spawn(move || sender.send((id, computer.join().unwrap())).unwrap());
+    }
+
+    for _ in 0..2 {
+        let (id, world) = built.recv_timeout(Duration::from_secs(10)).unwrap();
+        let mut plan = match id {
+            1 => MinecraftSerializer::dry_run()
+                .with_build(first)
+                .with_layout(Line),
+            _ => MinecraftSerializer::dry_run().with_build(second),
+        };
+        value.serialize(&mut plan).unwrap();
+
+        let mut expected: Vec<_> = plan.placed().iter().map(|(p, _)| *p).collect();
+        let mut found: Vec<_> = world.into_keys().collect();
+        expected.sort_by_key(|p| (p.x, p.y, p.z));
+        found.sort_by_key(|p| (p.x, p.y, p.z));
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn session_limit_test() {
+    use crate::mock;
+    use std::collections::HashMap;
+
+    let mut server = MinecraftServer::bind("127.0.0.1:0")
+        .unwrap()
+        .with_max_sessions(1)
+        .with_handshake_timeout(Duration::from_millis(200))
+        .serialize("limited".to_string());
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    // A computer that never says hello takes the only session, so the next one is hung up on.
+    let _idle = TcpStream::connect(address).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert!(tungstenite::connect(format!("ws://{address}")).is_err());
+
+    // Until the handshake times out and frees the session.
+    thread::sleep(Duration::from_millis(400));
+    let world = mock::computer(address, Role::Ser, 1, HashMap::new())
+        .join()
+        .unwrap();
+    assert!(!world.is_empty());
+}
+
+#[test]
+fn panicked_session_test() {
+    use crate::mock;
+    use std::{collections::HashMap, sync::atomic::AtomicBool};
+
+    let panicked = AtomicBool::new(false);
+    let mut server = MinecraftServer::bind("127.0.0.1:0")
+        .unwrap()
+        .with_max_sessions(1)
+        .on_ser(move |mut serializer| {
+            if !panicked.swap(true, Ordering::SeqCst) {
+                panic!("the handler broke");
+            }
+            "after".serialize(&mut serializer)?;
+            serializer.finish()
+        });
+    let address = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+
+    // The session whose handler panicked must give its slot back to the next computer.
+    let world = mock::computer(address, Role::Ser, 1, HashMap::new())
+        .join()
+        .unwrap();
+    assert!(world.is_empty());
+
+    thread::sleep(Duration::from_millis(100));
+    let world = mock::computer(address, Role::Ser, 1, HashMap::new())
+        .join()
+        .unwrap();
+    assert!(!world.is_empty());
+}
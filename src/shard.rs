@@ -0,0 +1,184 @@
+use std::thread;
+
+use serde::Serialize;
+
+use crate::{MinecraftError, MinecraftResult, MinecraftSerializer};
+
+/// Place one build through several computers at once.
+///
+/// `value` is serialized once with `plan`, a [`MinecraftSerializer::dry_run`] set up with the
+/// layout and build configuration of the build. Its block stream is then split into as many
+/// contiguous parts as there are `serializers`, and each computer places one part at the
+/// positions worked out by the plan, taking the build configuration of the plan. The settings of
+/// each serializer, such as overwrite protection and verification, apply to its own part, though
+/// mismatches are reported by their index in the whole block stream.
+///
+/// Returns once every computer has placed its part, or with the error of the first part that
+/// failed after waiting for the others. A part whose thread panicked counts as failed.
+pub fn serialize_sharded<T: Serialize + ?Sized>(
+    value: &T,
+    mut plan: MinecraftSerializer,
+    serializers: Vec<MinecraftSerializer>,
+) -> MinecraftResult<()> {
+    if serializers.is_empty() {
+        return Err(MinecraftError::Custom(
+            "a sharded build needs at least one computer".to_string(),
+        ));
+    }
+    if !plan.is_dry_run() {
+        return Err(MinecraftError::Custom(
+            "the plan of a sharded build must be a dry run".to_string(),
+        ));
+    }
+
+    value.serialize(&mut plan)?;
+    let blocks = plan.placed();
+    let len = blocks.len().div_ceil(serializers.len()).max(1);
+
+    let results: Vec<_> = thread::scope(|scope| {
+        let shards: Vec<_> = serializers
+            .into_iter()
+            .zip(blocks.chunks(len).chain(std::iter::repeat(&[][..])))
+            .enumerate()
+            .map(|(shard, (serializer, blocks))| {
+                let mut serializer = serializer.with_build(plan.build());
+                scope.spawn(move || {
+                    serializer.place_shard(shard * len, blocks)?;
+                    serializer.finish()
+                })
+            })
+            .collect();
+
+        shards
+            .into_iter()
+            .map(|shard| {
+                shard.join().unwrap_or_else(|_| {
+                    Err(MinecraftError::Custom("the shard panicked".to_string()))
+                })
+            })
+            .collect()
+    });
+
+    results
+        .into_iter()
+        .enumerate()
+        .find_map(|(shard, result)| result.err().map(|e| (shard, e)))
+        .map_or(Ok(()), |(shard, source)| {
+            Err(MinecraftError::ShardFailed {
+                shard,
+                source: Box::new(source),
+            })
+        })
+}
+
+#[test]
+fn sharded_test() {
+    use crate::{Line, MinecraftServer, Role, mock};
+    use std::collections::HashMap;
+
+    let value: Vec<u16> = (0..40).collect();
+    let mut server = MinecraftServer::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+
+    let computers: Vec<_> = (1..=3)
+        .map(|id| mock::computer(address, Role::Ser, id, HashMap::new()))
+        .collect();
+    let serializers = server.accept_serializers(3).unwrap();
+
+    let plan = || MinecraftSerializer::dry_run().with_layout(Line);
+    serialize_sharded(&value, plan(), serializers).unwrap();
+    let worlds: Vec<_> = computers.into_iter().map(|c| c.join().unwrap()).collect();
+
+    let mut expected = plan();
+    value.serialize(&mut expected).unwrap();
+    let mut placed = HashMap::new();
+    for world in worlds {
+        assert!(!world.is_empty());
+        for (position, block) in world {
+            assert!(placed.insert(position, block).is_none());
+        }
+    }
+    assert_eq!(placed.len(), expected.placed().len());
+    for (position, block) in expected.placed() {
+        assert_eq!(placed[position], block.to_string());
+    }
+
+    assert!(serialize_sharded(&value, plan(), Vec::new()).is_err());
+}
+
+#[test]
+fn sharded_verify_test() {
+    use crate::{Line, MinecraftServer, Role, mock};
+    use std::collections::HashMap;
+
+    let value: Vec<u16> = (0..20).collect();
+    let plan = || MinecraftSerializer::dry_run().with_layout(Line);
+    let mut expected = plan();
+    value.serialize(&mut expected).unwrap();
+    let last = expected.placed().len() - 1;
+    let (spawn, _) = &expected.placed()[last];
+
+    let mut server = MinecraftServer::bind("127.0.0.1:0").unwrap();
+    let address = server.local_addr().unwrap();
+    let protected = HashMap::from([(*spawn, mock::PROTECTED.to_string())]);
+    let computers = [
+        mock::computer(address, Role::Ser, 1, HashMap::new()),
+        mock::computer(address, Role::Ser, 2, protected),
+    ];
+    let mut serializers = server.accept_serializers(2).unwrap();
+    serializers.sort_by_key(|s| s.peer().unwrap().id);
+    let serializers = serializers
+        .into_iter()
+        .map(|s| s.with_verify(true))
+        .collect();
+
+    // The block missing from the second part is reported by its index in the whole build.
+    match serialize_sharded(&value, plan(), serializers) {
+        Err(MinecraftError::ShardFailed { shard: 1, source }) => match *source {
+            MinecraftError::Mismatched(mismatches) => {
+                assert_eq!(mismatches.len(), 1);
+                assert_eq!(mismatches[0].index, last);
+                assert_eq!(mismatches[0].position, *spawn);
+            }
+            other => panic!("expected a mismatch, got {other:?}"),
+        },
+        other => panic!("expected the second shard to fail, got {other:?}"),
+    }
+    for computer in computers {
+        computer.join().unwrap();
+    }
+}
+
+#[test]
+fn sharded_failure_test() {
+    use crate::Transport;
+
+    /// A computer whose connection panics on the first request.
+    struct Panicking;
+
+    impl Transport for Panicking {
+        fn send_frame(&mut self, _: String) -> MinecraftResult<()> {
+            panic!("the connection broke");
+        }
+
+        fn receive_frame(&mut self) -> MinecraftResult<String> {
+            unreachable!()
+        }
+    }
+
+    let value = vec![1u8, 2, 3];
+    let plan = MinecraftSerializer::new(Panicking);
+    assert!(matches!(
+        serialize_sharded(&value, plan, vec![MinecraftSerializer::dry_run()]),
+        Err(MinecraftError::Custom(_))
+    ));
+
+    let serializers = vec![
+        MinecraftSerializer::dry_run(),
+        MinecraftSerializer::new(Panicking),
+    ];
+    match serialize_sharded(&value, MinecraftSerializer::dry_run(), serializers) {
+        Err(MinecraftError::ShardFailed { shard: 1, .. }) => {}
+        other => panic!("expected the second shard to fail, got {other:?}"),
+    }
+}
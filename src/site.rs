@@ -0,0 +1,82 @@
+use std::sync::Arc;
+
+use crate::{
+    BuildConfig, Layout, MinecraftDeserializer, MinecraftSerializer, Palette, Peer, Spiral,
+};
+
+/// Picks out a computer by what it reported in the handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Computer {
+    Id(u32),
+    Label(String),
+}
+
+impl Computer {
+    pub fn matches(&self, peer: &Peer) -> bool {
+        match self {
+            Computer::Id(id) => peer.id == Some(*id),
+            Computer::Label(label) => peer.label.as_ref() == Some(label),
+        }
+    }
+}
+
+/// How the builds of one site are placed and read, shared by every session of the computers
+/// routed to it.
+#[derive(Clone)]
+pub struct Site {
+    build: BuildConfig,
+    layout: Arc<dyn Fn() -> Box<dyn Layout> + Send + Sync>,
+    palette: Palette,
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self::new(BuildConfig::default())
+    }
+}
+
+impl Site {
+    pub fn new(build: BuildConfig) -> Self {
+        Self {
+            build,
+            layout: Arc::new(|| Box::new(Spiral)),
+            palette: Palette::default(),
+        }
+    }
+
+    /// Give every session its own copy of `layout`, [`Spiral`] by default.
+    pub fn with_layout<L>(mut self, layout: L) -> Self
+    where
+        L: Layout + Clone + Send + Sync + 'static,
+    {
+        self.layout = Arc::new(move || Box::new(layout.clone()));
+        self
+    }
+
+    /// Build with the blocks of `palette`, and read builds back with them.
+    pub fn with_palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        self
+    }
+
+    pub fn build(&self) -> BuildConfig {
+        self.build
+    }
+
+    pub(crate) fn serializer(&self, serializer: MinecraftSerializer) -> MinecraftSerializer {
+        serializer
+            .with_build(self.build)
+            .with_boxed_layout((self.layout)())
+            .with_palette(self.palette.clone())
+    }
+
+    pub(crate) fn deserializer(
+        &self,
+        deserializer: MinecraftDeserializer,
+    ) -> MinecraftDeserializer {
+        deserializer
+            .with_build(self.build)
+            .with_boxed_layout((self.layout)())
+            .with_palette(self.palette.clone())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{MinecraftDeserializer, MinecraftResult, MinecraftSerializer};
+
+/// Stream a value from any self-describing format (JSON, TOML, ...) into the world.
+///
+/// Integer widths, strings and bytes are kept exactly as the source format reports them.
+pub fn transcode_to_minecraft<'de, D>(
+    deserializer: D,
+    serializer: &mut MinecraftSerializer,
+) -> MinecraftResult<()>
+where
+    D: serde::Deserializer<'de>,
+{
+    serde_transcode::transcode(deserializer, serializer)
+}
+
+/// Stream a value read from the world out into any serde format.
+///
+/// Structs are written as maps and enum variants as single-entry maps, keyed by variant name when
+/// the build has one and by index otherwise.
+pub fn transcode_from_minecraft<S>(
+    deserializer: &mut MinecraftDeserializer,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serde_transcode::transcode(deserializer, serializer)
+}
+
+#[test]
+fn transcode_json_test() {
+    let json = r#"{"name":"spawn","size":[16,-4,2.5],"tags":{"safe":true,"owner":null},"raw":"é"}"#;
+
+    let blocks = crate::mock::serialize_with(
+        |s| s,
+        |serializer| {
+            transcode_to_minecraft(&mut serde_json::Deserializer::from_str(json), serializer)
+        },
+    )
+    .unwrap();
+
+    let output = crate::mock::deserialize_with(
+        blocks,
+        |d| d,
+        |deserializer| {
+            let mut output = Vec::new();
+            transcode_from_minecraft(deserializer, &mut serde_json::Serializer::new(&mut output))
+                .map_err(|e| crate::MinecraftError::Custom(e.to_string()))?;
+            Ok(String::from_utf8(output)?)
+        },
+    )
+    .unwrap();
+
+    assert_eq!(output, json);
+}
@@ -0,0 +1,84 @@
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+
+use tungstenite::{Message, WebSocket, stream::MaybeTlsStream};
+
+use crate::{MinecraftError, MinecraftResult, Peer, Role, handshake};
+
+/// Carries the JSON text frames of the protocol between this crate and a computer.
+///
+/// Implemented for websockets over any stream `tungstenite` supports, whichever side opened them.
+pub trait Transport: Send {
+    fn send_frame(&mut self, frame: String) -> MinecraftResult<()>;
+
+    /// Wait for the next frame from the computer.
+    fn receive_frame(&mut self) -> MinecraftResult<String>;
+}
+
+impl<S: Read + Write + Send> Transport for WebSocket<S> {
+    fn send_frame(&mut self, frame: String) -> MinecraftResult<()> {
+        Ok(self.send(Message::text(frame))?)
+    }
+
+    fn receive_frame(&mut self) -> MinecraftResult<String> {
+        loop {
+            match self.read()? {
+                Message::Ping(_) | Message::Pong(_) => continue,
+                message => return Ok(message.to_text()?.to_owned()),
+            }
+        }
+    }
+}
+
+/// Connect to `url` (`ws://` or `wss://`), where a computer with `role` is waiting, for example
+/// behind a relay, and go through the handshake with it, asking for `token` if one is given.
+pub(crate) fn connect(
+    url: &str,
+    role: Role,
+    token: Option<&str>,
+) -> MinecraftResult<(WebSocket<MaybeTlsStream<TcpStream>>, Peer)> {
+    let (mut socket, _) = tungstenite::connect(url)?;
+    let peer = handshake(&mut socket, token)?;
+    if peer.role != role {
+        return Err(MinecraftError::Handshake(format!(
+            "expected a {role:?} computer at {url}, found {peer} connected as {:?}",
+            peer.role
+        )));
+    }
+    Ok((socket, peer))
+}
+
+#[test]
+fn connect_test() {
+    use crate::{MinecraftDeserializer, MinecraftSerializer, mock};
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    let value = ("relayed".to_string(), vec![1u8, 2, 3]);
+
+    let (url, relay) = mock::relay(Role::Ser, 1, HashMap::new());
+    let mut serializer = MinecraftSerializer::connect(&url, None).unwrap();
+    assert_eq!(serializer.peer().unwrap().id, Some(1));
+    value.serialize(&mut serializer).unwrap();
+    serializer.finish().unwrap();
+    drop(serializer);
+    let world = relay.join().unwrap();
+
+    let (url, relay) = mock::relay(Role::De, 1, world);
+    let mut deserializer = MinecraftDeserializer::connect(&url, None).unwrap();
+    assert_eq!(
+        <(String, Vec<u8>)>::deserialize(&mut deserializer).unwrap(),
+        value
+    );
+    drop(deserializer);
+    relay.join().unwrap();
+
+    let (url, relay) = mock::relay(Role::De, 2, HashMap::new());
+    assert!(matches!(
+        MinecraftSerializer::connect(&url, None),
+        Err(MinecraftError::Handshake(_))
+    ));
+    relay.join().unwrap();
+}
@@ -0,0 +1,648 @@
+use std::{
+    cell::Cell,
+    collections::HashSet,
+    fmt,
+    sync::{Mutex, OnceLock},
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{EnumAccess, Error, MapAccess, SeqAccess, VariantAccess, Visitor},
+    ser::{
+        SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant,
+    },
+};
+
+/// Name of the newtype struct `Value` asks for when deserializing.
+///
+/// `MinecraftDeserializer` recognises it and describes named constructs (structs, tuples,
+/// enum variants) as [`Tagged`] enums, so no information is lost on the way back.
+pub(crate) const TOKEN: &str = "$serdecraft::private::Value";
+
+/// A dynamically typed value covering every kind the block format can represent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+
+    F32(f32),
+    F64(f64),
+
+    Char(char),
+    String(String),
+    Bytes(Vec<u8>),
+
+    Option(Option<Box<Value>>),
+    Unit,
+    NewtypeStruct(String, Box<Value>),
+
+    Seq(Vec<Value>),
+    Tuple(Vec<Value>),
+    TupleStruct(String, Vec<Value>),
+
+    /// Map entries, kept in the order they were written.
+    Map(Vec<(Value, Value)>),
+    Struct(String, Vec<(String, Value)>),
+
+    UnitVariant(Variant),
+    NewtypeVariant(Variant, Box<Value>),
+    TupleVariant(Variant, Vec<Value>),
+    StructVariant(Variant, Vec<(String, Value)>),
+}
+
+/// How an enum variant is identified in the world, depending on the [`EnumRepr`] used.
+///
+/// Writing a variant with a representation that needs a part it was read without fails with
+/// [`MinecraftError::VariantRepr`]. Other formats are given such a variant as a single-entry map
+/// keyed by the part it has.
+///
+/// [`EnumRepr`]: crate::EnumRepr
+/// [`MinecraftError::VariantRepr`]: crate::MinecraftError::VariantRepr
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Variant {
+    Index(u32),
+    Name(String),
+    NameAndIndex(String, u32),
+}
+
+/// Stand in for the part of a [`Variant`] that was not read, so that `MinecraftSerializer`
+/// refuses to write a representation that needs it instead of making one up.
+pub(crate) const UNKNOWN_VARIANT_NAME: &str = "$serdecraft::private::UnknownVariant";
+pub(crate) const UNKNOWN_VARIANT_INDEX: u32 = u32::MAX;
+
+/// Name of the newtype struct a variant missing its index or name is wrapped in.
+///
+/// `MinecraftSerializer` recognises it and is handed the variant with placeholders for the
+/// missing part. Other formats never see the placeholders: they get a single-entry map keyed by
+/// the part that is known, as [`crate::transcode_from_minecraft`] writes variants.
+pub(crate) const VARIANT_TOKEN: &str = "$serdecraft::private::Variant";
+
+thread_local! {
+    /// Whether the serializer at work is `MinecraftSerializer` inside a [`VARIANT_TOKEN`].
+    static PLACEHOLDERS: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run `f`, in which [`Incomplete`] variants hand over the placeholders of [`Variant::parts`].
+pub(crate) fn with_placeholders<R>(f: impl FnOnce() -> R) -> R {
+    let outer = PLACEHOLDERS.replace(true);
+    let result = f();
+    PLACEHOLDERS.set(outer);
+    result
+}
+
+impl Variant {
+    /// The index and name handed to serializers, with a placeholder for whichever is unknown.
+    fn parts<E: serde::ser::Error>(&self) -> Result<(u32, &'static str), E> {
+        Ok(match self {
+            Variant::Index(index) => (*index, UNKNOWN_VARIANT_NAME),
+            Variant::Name(name) => (UNKNOWN_VARIANT_INDEX, intern(name)?),
+            Variant::NameAndIndex(name, index) => (*index, intern(name)?),
+        })
+    }
+}
+
+/// Named constructs that a generic visitor cannot tell apart from plain sequences and maps.
+///
+/// The deserializer hands them to [`Value`] as enum variants identified by their index.
+#[repr(u32)]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Tagged {
+    NewtypeStruct,
+    Tuple,
+    TupleStruct,
+    Struct,
+    UnitVariant,
+    NewtypeVariant,
+    TupleVariant,
+    StructVariant,
+}
+
+/// How many distinct names [`intern`] leaks before it refuses new ones.
+const MAX_NAMES: usize = 1 << 16;
+
+/// Serde requires `&'static str` names, so names read from the world are leaked once and reused.
+///
+/// Past [`MAX_NAMES`] distinct names, new ones are refused so that serializing values decoded
+/// from untrusted builds cannot leak memory without bound.
+fn intern<E: serde::ser::Error>(name: &str) -> Result<&'static str, E> {
+    static NAMES: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+
+    let mut names = NAMES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    if let Some(name) = names.get(name) {
+        return Ok(name);
+    }
+    if names.len() >= MAX_NAMES {
+        return Err(E::custom(format!(
+            "more than {MAX_NAMES} distinct names, refusing `{name}`"
+        )));
+    }
+
+    let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+    names.insert(name);
+    Ok(name)
+}
+
+impl Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Value::Bool(v) => serializer.serialize_bool(*v),
+            Value::I8(v) => serializer.serialize_i8(*v),
+            Value::I16(v) => serializer.serialize_i16(*v),
+            Value::I32(v) => serializer.serialize_i32(*v),
+            Value::I64(v) => serializer.serialize_i64(*v),
+            Value::U8(v) => serializer.serialize_u8(*v),
+            Value::U16(v) => serializer.serialize_u16(*v),
+            Value::U32(v) => serializer.serialize_u32(*v),
+            Value::U64(v) => serializer.serialize_u64(*v),
+            Value::F32(v) => serializer.serialize_f32(*v),
+            Value::F64(v) => serializer.serialize_f64(*v),
+            Value::Char(v) => serializer.serialize_char(*v),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Bytes(v) => serializer.serialize_bytes(v),
+            Value::Option(None) => serializer.serialize_none(),
+            Value::Option(Some(v)) => serializer.serialize_some(v),
+            Value::Unit => serializer.serialize_unit(),
+            Value::NewtypeStruct(name, v) => serializer.serialize_newtype_struct(intern(name)?, v),
+            Value::Seq(values) => {
+                let mut seq = serializer.serialize_seq(Some(values.len()))?;
+                for v in values {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Value::Tuple(values) => {
+                let mut tuple = serializer.serialize_tuple(values.len())?;
+                for v in values {
+                    tuple.serialize_element(v)?;
+                }
+                tuple.end()
+            }
+            Value::TupleStruct(name, values) => {
+                let mut tuple = serializer.serialize_tuple_struct(intern(name)?, values.len())?;
+                for v in values {
+                    tuple.serialize_field(v)?;
+                }
+                tuple.end()
+            }
+            Value::Map(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Struct(name, fields) => {
+                let mut st = serializer.serialize_struct(intern(name)?, fields.len())?;
+                for (k, v) in fields {
+                    st.serialize_field(intern(k)?, v)?;
+                }
+                st.end()
+            }
+            Value::UnitVariant(variant)
+            | Value::NewtypeVariant(variant, _)
+            | Value::TupleVariant(variant, _)
+            | Value::StructVariant(variant, _) => match variant {
+                Variant::NameAndIndex(..) => self.serialize_variant(serializer),
+                _ => serializer.serialize_newtype_struct(VARIANT_TOKEN, &Incomplete(self)),
+            },
+        }
+    }
+}
+
+impl Value {
+    /// Serialize a variant as one, with placeholders for whichever part it is missing.
+    fn serialize_variant<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::UnitVariant(variant) => {
+                let (index, name) = variant.parts()?;
+                serializer.serialize_unit_variant("", index, name)
+            }
+            Value::NewtypeVariant(variant, v) => {
+                let (index, name) = variant.parts()?;
+                serializer.serialize_newtype_variant("", index, name, v)
+            }
+            Value::TupleVariant(variant, values) => {
+                let (index, name) = variant.parts()?;
+                let mut tv = serializer.serialize_tuple_variant("", index, name, values.len())?;
+                for v in values {
+                    tv.serialize_field(v)?;
+                }
+                tv.end()
+            }
+            Value::StructVariant(variant, fields) => {
+                let (index, name) = variant.parts()?;
+                let mut sv = serializer.serialize_struct_variant("", index, name, fields.len())?;
+                for (k, v) in fields {
+                    sv.serialize_field(intern(k)?, v)?;
+                }
+                sv.end()
+            }
+            _ => unreachable!("only variants are serialized as variants"),
+        }
+    }
+}
+
+/// A variant missing its index or name, see [`VARIANT_TOKEN`].
+struct Incomplete<'a>(&'a Value);
+
+impl Serialize for Incomplete<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if PLACEHOLDERS.get() {
+            return self.0.serialize_variant(serializer);
+        }
+
+        let (variant, payload) = match self.0 {
+            Value::UnitVariant(variant) => (variant, Value::Unit),
+            Value::NewtypeVariant(variant, v) => (variant, (**v).clone()),
+            Value::TupleVariant(variant, values) => (variant, Value::Seq(values.clone())),
+            Value::StructVariant(variant, fields) => {
+                let fields = fields
+                    .iter()
+                    .map(|(k, v)| (Value::String(k.clone()), v.clone()))
+                    .collect();
+                (variant, Value::Map(fields))
+            }
+            _ => unreachable!("only variants are incomplete"),
+        };
+
+        let mut map = serializer.serialize_map(Some(1))?;
+        match variant {
+            Variant::Index(index) => map.serialize_entry(index, &payload)?,
+            Variant::Name(name) | Variant::NameAndIndex(name, _) => {
+                map.serialize_entry(name, &payload)?
+            }
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(TOKEN, ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any value")
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+
+    fn visit_i8<E: Error>(self, v: i8) -> Result<Value, E> {
+        Ok(Value::I8(v))
+    }
+
+    fn visit_i16<E: Error>(self, v: i16) -> Result<Value, E> {
+        Ok(Value::I16(v))
+    }
+
+    fn visit_i32<E: Error>(self, v: i32) -> Result<Value, E> {
+        Ok(Value::I32(v))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::I64(v))
+    }
+
+    fn visit_u8<E: Error>(self, v: u8) -> Result<Value, E> {
+        Ok(Value::U8(v))
+    }
+
+    fn visit_u16<E: Error>(self, v: u16) -> Result<Value, E> {
+        Ok(Value::U16(v))
+    }
+
+    fn visit_u32<E: Error>(self, v: u32) -> Result<Value, E> {
+        Ok(Value::U32(v))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::U64(v))
+    }
+
+    fn visit_f32<E: Error>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::F32(v))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::F64(v))
+    }
+
+    fn visit_char<E: Error>(self, v: char) -> Result<Value, E> {
+        Ok(Value::Char(v))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(v.to_owned()))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Bytes(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Bytes(v))
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Option(None))
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(Value::Option(Some(Box::new(value))))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Unit)
+    }
+
+    /// Formats that do not know about [`TOKEN`] end up here and describe themselves as usual.
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(v) = seq.next_element()? {
+            values.push(v);
+        }
+        Ok(Value::Seq(values))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = Vec::new();
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(Value::Map(entries))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (tagged, variant) = data.variant::<Tagged>()?;
+        variant.tuple_variant(0, TaggedVisitor(tagged))
+    }
+}
+
+impl<'de> Deserialize<'de> for Variant {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(VariantVisitor)
+    }
+}
+
+/// Accepts an index, a name, or a single `name => index` entry.
+struct VariantVisitor;
+
+impl<'de> Visitor<'de> for VariantVisitor {
+    type Value = Variant;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a variant index or name")
+    }
+
+    fn visit_u32<E: Error>(self, v: u32) -> Result<Variant, E> {
+        Ok(Variant::Index(v))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Variant, E> {
+        u32::try_from(v)
+            .map(Variant::Index)
+            .map_err(|_| E::custom("variant index out of range"))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Variant, E> {
+        Ok(Variant::Name(v.to_owned()))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Variant, E> {
+        Ok(Variant::Name(v))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Variant, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let (name, index) = map
+            .next_entry()?
+            .ok_or_else(|| A::Error::invalid_length(0, &self))?;
+        Ok(Variant::NameAndIndex(name, index))
+    }
+}
+
+/// Reads the header and payload of a [`Tagged`] construct, presented as a flat sequence.
+struct TaggedVisitor(Tagged);
+
+impl TaggedVisitor {
+    fn next<'de, A, T>(seq: &mut A, index: usize) -> Result<T, A::Error>
+    where
+        A: SeqAccess<'de>,
+        T: Deserialize<'de>,
+    {
+        seq.next_element()?
+            .ok_or_else(|| A::Error::invalid_length(index, &"a complete header"))
+    }
+
+    fn values<'de, A>(seq: &mut A) -> Result<Vec<Value>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut values = Vec::new();
+        while let Some(v) = seq.next_element()? {
+            values.push(v);
+        }
+        Ok(values)
+    }
+
+    fn fields<'de, A>(seq: &mut A) -> Result<Vec<(String, Value)>, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut fields = Vec::new();
+        while let Some(key) = seq.next_element()? {
+            let value = Self::next(seq, 2 * fields.len() + 1)?;
+            fields.push((key, value));
+        }
+        Ok(fields)
+    }
+}
+
+impl<'de> Visitor<'de> for TaggedVisitor {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "the contents of a {:?}", self.0)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        Ok(match self.0 {
+            Tagged::NewtypeStruct => {
+                let name = Self::next(&mut seq, 0)?;
+                let value = Self::next(&mut seq, 1)?;
+                Value::NewtypeStruct(name, Box::new(value))
+            }
+            Tagged::Tuple => Value::Tuple(Self::values(&mut seq)?),
+            Tagged::TupleStruct => {
+                let name = Self::next(&mut seq, 0)?;
+                let _len: u32 = Self::next(&mut seq, 1)?;
+                Value::TupleStruct(name, Self::values(&mut seq)?)
+            }
+            Tagged::Struct => {
+                let name = Self::next(&mut seq, 0)?;
+                let _len: u32 = Self::next(&mut seq, 1)?;
+                Value::Struct(name, Self::fields(&mut seq)?)
+            }
+            Tagged::UnitVariant => Value::UnitVariant(Self::next(&mut seq, 0)?),
+            Tagged::NewtypeVariant => {
+                let index = Self::next(&mut seq, 0)?;
+                let value = Self::next(&mut seq, 1)?;
+                Value::NewtypeVariant(index, Box::new(value))
+            }
+            Tagged::TupleVariant => {
+                let index = Self::next(&mut seq, 0)?;
+                let _len: u32 = Self::next(&mut seq, 1)?;
+                Value::TupleVariant(index, Self::values(&mut seq)?)
+            }
+            Tagged::StructVariant => {
+                let index = Self::next(&mut seq, 0)?;
+                let _len: u32 = Self::next(&mut seq, 1)?;
+                Value::StructVariant(index, Self::fields(&mut seq)?)
+            }
+        })
+    }
+}
+
+#[test]
+fn value_round_trip_test() {
+    let value = Value::Struct(
+        "Build".to_string(),
+        vec![
+            ("flag".to_string(), Value::Bool(true)),
+            ("small".to_string(), Value::I8(-3)),
+            ("wide".to_string(), Value::U64(123_456_789_012)),
+            ("ratio".to_string(), Value::F64(0.125)),
+            ("letter".to_string(), Value::Char('😎')),
+            ("bytes".to_string(), Value::Bytes(b"raw".to_vec())),
+            ("none".to_string(), Value::Option(None)),
+            (
+                "some".to_string(),
+                Value::Option(Some(Box::new(Value::String("text".to_string())))),
+            ),
+            ("unit".to_string(), Value::Unit),
+            (
+                "newtype".to_string(),
+                Value::NewtypeStruct("Meters".to_string(), Box::new(Value::F32(1.5))),
+            ),
+            (
+                "seq".to_string(),
+                Value::Seq(vec![Value::U16(1), Value::U16(300)]),
+            ),
+            (
+                "tuple".to_string(),
+                Value::Tuple(vec![Value::I32(-1), Value::Unit]),
+            ),
+            (
+                "tuple_struct".to_string(),
+                Value::TupleStruct("Pair".to_string(), vec![Value::I64(7), Value::U8(8)]),
+            ),
+            (
+                "map".to_string(),
+                Value::Map(vec![(Value::String("key".to_string()), Value::I16(-300))]),
+            ),
+            (
+                "unit_variant".to_string(),
+                Value::UnitVariant(Variant::Index(2)),
+            ),
+            (
+                "newtype_variant".to_string(),
+                Value::NewtypeVariant(Variant::Index(0), Box::new(Value::U32(91))),
+            ),
+            (
+                "tuple_variant".to_string(),
+                Value::TupleVariant(Variant::Index(1), vec![Value::Bool(false)]),
+            ),
+            (
+                "struct_variant".to_string(),
+                Value::StructVariant(Variant::Index(3), vec![("x".to_string(), Value::U8(255))]),
+            ),
+        ],
+    );
+
+    let blocks = crate::mock::serialize(&value).unwrap();
+    let decoded: Value = crate::mock::deserialize(blocks.clone()).unwrap();
+    assert_eq!(decoded, value);
+    assert_eq!(crate::mock::serialize(&decoded).unwrap(), blocks);
+
+    // A variant read by index only has no name to write.
+    let unit = Value::UnitVariant(Variant::Index(2));
+    assert!(matches!(
+        crate::mock::serialize_with(
+            |s| s.with_enum_repr(crate::EnumRepr::Name),
+            |s| unit.serialize(s)
+        ),
+        Err(crate::MinecraftError::VariantRepr(crate::EnumRepr::Name))
+    ));
+
+    // Other formats are not handed the placeholder, but the part that is known.
+    assert_eq!(serde_json::to_string(&unit).unwrap(), r#"{"2":null}"#);
+    let named = Value::TupleVariant(Variant::Name("Pair".to_string()), vec![Value::U8(1)]);
+    assert_eq!(serde_json::to_string(&named).unwrap(), r#"{"Pair":[1]}"#);
+
+    // Serializing the same names again leaks nothing new.
+    let name = intern::<crate::MinecraftError>("Build").unwrap();
+    assert!(std::ptr::eq(
+        name,
+        intern::<crate::MinecraftError>("Build").unwrap()
+    ));
+}